@@ -0,0 +1,86 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+/// remembers what glob patterns expanded to, so big trees don't have to be walked every time
+///
+/// a cached expansion is only invalidated when the modification time of the directory the
+/// pattern starts in changes, which doesn't happen for changes further down the tree
+pub struct GlobCache {
+    path: PathBuf,
+    patterns: HashMap<String, CachedGlob>,
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedGlob {
+    root_modified: SystemTime,
+    paths: Vec<PathBuf>,
+}
+
+impl GlobCache {
+    /// reads the cache at `path`, starting with an empty one if it's missing or unreadable
+    pub fn load(path: &Path) -> GlobCache {
+        let patterns = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        GlobCache {
+            path: path.to_path_buf(),
+            patterns,
+            dirty: false,
+        }
+    }
+
+    /// writes the cache back if anything changed
+    pub fn save(&mut self) -> color_eyre::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        std::fs::write(&self.path, serde_json::to_string(&self.patterns)?)?;
+        self.dirty = false;
+
+        Ok(())
+    }
+
+    /// the paths `pattern` expanded to, if `root` wasn't modified since
+    pub fn get(&self, pattern: &str, root: &Path) -> Option<&[PathBuf]> {
+        let cached = self.patterns.get(pattern)?;
+        (modified(root).ok()? == cached.root_modified).then_some(cached.paths.as_slice())
+    }
+
+    pub fn insert(&mut self, pattern: &str, root: &Path, paths: Vec<PathBuf>) {
+        let Ok(root_modified) = modified(root) else {
+            return;
+        };
+
+        self.patterns.insert(
+            pattern.to_string(),
+            CachedGlob {
+                root_modified,
+                paths,
+            },
+        );
+        self.dirty = true;
+    }
+}
+
+/// removes the cache file, returning whether there was one
+pub fn clear_cache(path: &Path) -> color_eyre::Result<bool> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(eyre!("unable to remove {}: {err}", path.display())),
+    }
+}
+
+fn modified(path: &Path) -> std::io::Result<SystemTime> {
+    std::fs::metadata(path)?.modified()
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use color_eyre::eyre::Context;
+use serde::{Deserialize, Deserializer};
+
+pub const CONFIG_FILENAME: &str = "config.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// terminal used by `open-term` and `open-gui` to open a new tab or window
+    pub terminal: Terminal,
+
+    /// multiplexer started for the project, either directly or inside the terminal
+    pub multiplexer: Multiplexer,
+
+    /// programs used by `open-gui` to select an entry, the first one that is installed is used
+    #[serde(deserialize_with = "one_or_many")]
+    pub gui_chooser: Vec<GuiChooser>,
+
+    /// editor used by `open-editor` if `$EDITOR` isn't set
+    pub editor: Option<String>,
+
+    /// show nerd font icons in the pickers and highlight the last path component in the
+    /// terminal ones
+    pub decorations: bool,
+
+    /// format the project list is created in, an existing `projects.toml` is always used
+    pub data_format: DataFormat,
+
+    /// patterns expanding to more paths than this are warned about, 0 disables the warning
+    pub max_pattern_matches: Option<usize>,
+
+    /// shown after entries in the pickers which have a running session, an empty one disables it
+    pub session_marker: Option<String>,
+
+    /// directory `clone` clones repositories into if no target is given
+    pub projects_root: Option<String>,
+
+    /// start sessions in the git repository an entry is in instead of its path, entries with a
+    /// `cwd` keep it
+    pub cwd_from_git_root: bool,
+
+    /// days after which an open counts half as much when ordering by frecency, 0 disables the
+    /// decay
+    pub frecency_half_life: Option<u64>,
+}
+
+impl Config {
+    /// reads the config at `path`, falling back to the defaults if there is none
+    pub fn load(path: &Path) -> color_eyre::Result<Config> {
+        if !path.try_exists()? {
+            return Ok(Config::default());
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).wrap_err_with(|| format!("invalid config at {}", path.display()))
+    }
+
+    /// how many paths a pattern may expand to before it's warned about, `None` if it never is
+    pub fn pattern_match_warning(&self) -> Option<usize> {
+        match self.max_pattern_matches {
+            Some(0) => None,
+            Some(max) => Some(max),
+            None => Some(500),
+        }
+    }
+
+    /// the configured session marker or `●`, `None` if it's disabled
+    pub fn session_marker(&self) -> Option<&str> {
+        match self.session_marker.as_deref() {
+            Some("") => None,
+            Some(marker) => Some(marker),
+            None => Some("●"),
+        }
+    }
+
+    /// the half-life of opens in days, 14 by default and infinite if the decay is disabled
+    pub fn frecency_half_life(&self) -> f64 {
+        match self.frecency_half_life {
+            Some(0) => f64::INFINITY,
+            Some(days) => days as f64,
+            None => 14.0,
+        }
+    }
+
+    /// the configured gui choosers or the default ones for this os
+    pub fn gui_choosers(&self) -> Vec<GuiChooser> {
+        if !self.gui_chooser.is_empty() {
+            return self.gui_chooser.clone();
+        }
+
+        if cfg!(target_os = "macos") {
+            vec![GuiChooser::Choose]
+        } else {
+            vec![
+                GuiChooser::Anyrun,
+                GuiChooser::Rofi,
+                GuiChooser::Wofi,
+                GuiChooser::Fuzzel,
+            ]
+        }
+    }
+}
+
+/// allows writing `key = "value"` instead of `key = ["value"]`
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Terminal {
+    Wezterm,
+    Kitty,
+    /// windows terminal
+    #[serde(rename = "windows-terminal", alias = "wt")]
+    Wt,
+}
+
+impl Terminal {
+    pub fn program(self) -> &'static str {
+        match self {
+            Terminal::Wezterm => "wezterm",
+            Terminal::Kitty => "kitty",
+            Terminal::Wt => "wt.exe",
+        }
+    }
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        if cfg!(windows) {
+            Terminal::Wt
+        } else {
+            Terminal::Wezterm
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Multiplexer {
+    #[default]
+    Tmux,
+    Zellij,
+    /// just a shell in the project's directory
+    None,
+}
+
+impl Multiplexer {
+    pub fn program(self) -> Option<&'static str> {
+        match self {
+            Multiplexer::Tmux => Some("tmux"),
+            Multiplexer::Zellij => Some("zellij"),
+            Multiplexer::None => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    #[default]
+    Json,
+    Toml,
+}
+
+impl DataFormat {
+    /// the format of the project list at `path`, told by its extension
+    pub fn of(path: &Path) -> DataFormat {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            DataFormat::Toml
+        } else {
+            DataFormat::Json
+        }
+    }
+
+    /// name of the project list in the data directory
+    pub fn filename(self) -> &'static str {
+        match self {
+            DataFormat::Json => "projects.json",
+            DataFormat::Toml => "projects.toml",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GuiChooser {
+    Anyrun,
+    Rofi,
+    Wofi,
+    Fuzzel,
+    Choose,
+}
+
+impl GuiChooser {
+    pub fn program(self) -> &'static str {
+        match self {
+            GuiChooser::Anyrun => "anyrun",
+            GuiChooser::Rofi => "rofi",
+            GuiChooser::Wofi => "wofi",
+            GuiChooser::Fuzzel => "fuzzel",
+            GuiChooser::Choose => "choose",
+        }
+    }
+
+    /// whether the chooser can print the index of the selected line instead of its content
+    pub fn returns_index(self) -> bool {
+        matches!(
+            self,
+            GuiChooser::Rofi | GuiChooser::Fuzzel | GuiChooser::Choose
+        )
+    }
+}
@@ -1,47 +1,963 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{hash_map, HashMap, HashSet, VecDeque},
     fmt::Display,
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use color_eyre::eyre::eyre;
-use serde::{Deserialize, Serialize};
+use color_eyre::eyre::{eyre, Context};
+use ignore::gitignore::Gitignore;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    cache::GlobCache,
+    config::{Config, DataFormat},
+    open::BackendKind,
+    parallel::parallel_map,
+};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
-pub struct Entry(pub PathBuf);
+#[serde(remote = "Self")]
+pub struct Entry {
+    pub path: PathBuf,
+
+    /// name used for the tmux session instead of the last path component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// shown next to the path in the pickers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// glob patterns, expanded paths matching any of them are skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+
+    /// how many components an expanded path may have below the first component of the pattern
+    /// containing a wildcard, so `~/code/**` with a depth of 1 only matches `~/code/<project>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+
+    /// also keep expanded paths which aren't directories
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_files: bool,
+
+    /// shell command run in newly created sessions, passed to tmux as a single argument so it's
+    /// interpreted by the shell there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<String>,
+
+    /// directory sessions and editors are started in instead of the path, relative paths are
+    /// relative to the path, so they also work for patterns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+
+    /// names of the windows new tmux sessions are created with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<String>,
+
+    /// files within the path the editor can be opened at instead of the path, relative to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<PathBuf>,
+
+    /// listed before all other entries in the pickers
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+
+    /// hidden everywhere but `list --all` and the pickers with `--include-archived`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+
+    /// how `open` opens the entry if no backend is given, `None` for the configured multiplexer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<BackendKind>,
+
+    /// unix timestamp (in seconds) of the last time this entry was opened
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub open_count: u32,
+
+    /// for patterns, the path they expanded to that was opened last
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened_path: Option<PathBuf>,
+
+    /// index of the stored entry this one was expanded from
+    #[serde(skip)]
+    pub source: Option<usize>,
+
+    /// the pattern this entry was expanded from, if the stored entry is one
+    #[serde(skip)]
+    pub pattern: Option<PathBuf>,
+}
 
 impl Entry {
-    fn with_path(mut self, path: PathBuf) -> Entry {
-        self.0 = path;
+    pub fn new(path: PathBuf) -> Entry {
+        Entry {
+            path,
+            name: None,
+            description: None,
+            tags: Vec::new(),
+            exclude: Vec::new(),
+            max_depth: None,
+            allow_files: false,
+            run: None,
+            cwd: None,
+            windows: Vec::new(),
+            files: Vec::new(),
+            pinned: false,
+            archived: false,
+            backend: None,
+            last_opened: None,
+            open_count: 0,
+            last_opened_path: None,
+            source: None,
+            pattern: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: PathBuf) -> Entry {
+        self.path = path;
         self
     }
+
+    pub fn mark_opened(&mut self) {
+        self.last_opened = Some(unix_now());
+        self.open_count = self.open_count.saturating_add(1);
+    }
+
+    /// how often the entry was opened, halved for every `half_life` days between that last
+    /// happening and `now`, so projects that were used a lot long ago don't outrank the current
+    /// ones
+    pub fn frecency_score(&self, half_life: f64, now: u64) -> f64 {
+        let Some(last_opened) = self.last_opened else {
+            return 0.0;
+        };
+
+        let days = now.saturating_sub(last_opened) as f64 / 86_400.0;
+
+        self.open_count as f64 * 0.5f64.powf(days / half_life)
+    }
+
+    /// the path with `~` and environment variables expanded
+    pub fn expanded_path(&self) -> color_eyre::Result<PathBuf> {
+        expand_path(&self.path)
+    }
+
+    /// whether the path contains glob syntax and may expand to other paths, which remote paths
+    /// never do
+    pub fn is_pattern(&self) -> bool {
+        is_glob_pattern(&self.path) && self.remote().is_none()
+    }
+
+    /// the host and the path on it for entries like `ssh://host/path/to/project`
+    pub fn remote(&self) -> Option<(String, PathBuf)> {
+        parse_remote(&self.path)
+    }
+
+    /// takes over metadata from a duplicate of this entry without overriding what's already set
+    pub fn merge(&mut self, other: Entry) {
+        if self.name.is_none() {
+            self.name = other.name;
+        }
+        if self.description.is_none() {
+            self.description = other.description;
+        }
+        if self.run.is_none() {
+            self.run = other.run;
+        }
+        if self.cwd.is_none() {
+            self.cwd = other.cwd;
+        }
+        if self.windows.is_empty() {
+            self.windows = other.windows;
+        }
+        if self.backend.is_none() {
+            self.backend = other.backend;
+        }
+        self.pinned |= other.pinned;
+        // the duplicate is still in use if either of them is
+        self.archived &= other.archived;
+        for file in other.files {
+            if !self.files.contains(&file) {
+                self.files.push(file);
+            }
+        }
+        for tag in other.tags {
+            if !self.has_tag(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        for pattern in other.exclude {
+            if !self.exclude.contains(&pattern) {
+                self.exclude.push(pattern);
+            }
+        }
+        if other.last_opened > self.last_opened {
+            self.last_opened_path = other.last_opened_path;
+        }
+        self.last_opened = self.last_opened.max(other.last_opened);
+        self.open_count = self.open_count.saturating_add(other.open_count);
+    }
+
+    /// the directory to start in, the [`cwd`](Entry::cwd) if there is one, otherwise the path
+    pub fn working_dir(&self) -> PathBuf {
+        match &self.cwd {
+            Some(cwd) => self.path.join(cwd),
+            None => self.path.clone(),
+        }
+    }
+
+    /// nerd font icon for the entry, a branch for git repositories and a folder otherwise
+    pub fn icon(&self) -> &'static str {
+        if self.path.join(".git").exists() {
+            "\u{e725}"
+        } else {
+            "\u{f07b}"
+        }
+    }
+
+    /// the entry as shown in the pickers, followed by the description if there is one
+    pub fn label(&self) -> String {
+        match &self.description {
+            Some(description) => format!("{self}  — {description}"),
+            None => self.to_string(),
+        }
+    }
+
+    /// the [label](Entry::label) as shown in terminals with colors, with a dimmed description
+    /// and, if decorations are enabled, the [icon](Entry::icon), a dimmed parent and a bold last
+    /// component
+    pub fn decorated_label(&self, config: &Config) -> String {
+        let mut label = if config.decorations {
+            let path = self.to_string();
+            let (parent, name) = match path.rfind(std::path::MAIN_SEPARATOR) {
+                Some(idx) => path.split_at(idx + 1),
+                None => ("", path.as_str()),
+            };
+            format!("{} \x1b[2m{parent}\x1b[0m\x1b[1m{name}\x1b[0m", self.icon())
+        } else {
+            self.to_string()
+        };
+        if let Some(description) = &self.description {
+            label.push_str(&format!("  \x1b[2m— {description}\x1b[0m"));
+        }
+
+        label
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// the custom name if there is one, otherwise the last path component
+    pub fn session_name(&self) -> Option<String> {
+        self.name.clone().or_else(|| {
+            self.path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+    }
+}
+
+impl Serialize for Entry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Entry::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Entry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// older versions stored every entry as a bare path
+        // only lives during deserialization, boxing the entry isn't worth it
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Path(PathBuf),
+            #[serde(with = "Entry")]
+            Full(Entry),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Path(path) => Entry::new(path),
+            Repr::Full(entry) => entry,
+        })
+    }
 }
 
 impl Display for Entry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.0)
+        write!(f, "{}", display_path(&self.path))
+    }
+}
+
+/// the host and the absolute path on it of a `ssh://host/path` url, `None` for local paths
+pub fn parse_remote(path: &Path) -> Option<(String, PathBuf)> {
+    let (host, path) = path.to_str()?.strip_prefix("ssh://")?.split_once('/')?;
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    Some((host.to_string(), Path::new("/").join(path)))
+}
+
+/// the path with the home directory abbreviated to `~`
+pub fn display_path(path: &Path) -> String {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+
+    match home.and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// version of the project list format written by [`ProjectList`]
+pub const FORMAT_VERSION: u64 = 2;
+
+/// the project list as it's stored, version 1 was a bare array of entries
+#[derive(Serialize, Deserialize)]
+pub struct ProjectList<E> {
+    pub version: u64,
+    pub entries: E,
+}
+
+impl<'a> ProjectList<&'a VecDeque<Entry>> {
+    pub fn new(entries: &'a VecDeque<Entry>) -> Self {
+        ProjectList {
+            version: FORMAT_VERSION,
+            entries,
+        }
+    }
+}
+
+/// parses a project list in any of the formats written so far, returning whether it's outdated
+/// and should be written again in the current one
+pub fn migrate_entries(
+    content: &str,
+    format: DataFormat,
+) -> color_eyre::Result<(VecDeque<Entry>, bool)> {
+    let list: ProjectList<VecDeque<Entry>> = match format {
+        DataFormat::Json => {
+            if content.trim_start().starts_with('[') {
+                return Ok((serde_json::from_str(content)?, true));
+            }
+            serde_json::from_str(content)?
+        }
+        // toml lists have always been written with a version
+        DataFormat::Toml => toml::from_str(content)?,
+    };
+
+    if list.version > FORMAT_VERSION {
+        return Err(eyre!(
+            "the project list has version {}, but only versions up to {FORMAT_VERSION} are \
+             supported, is open-project outdated?",
+            list.version
+        ));
+    }
+
+    Ok((list.entries, list.version < FORMAT_VERSION))
+}
+
+/// the project list in the current format, entries are an array of tables in toml
+pub fn serialize_entries(
+    entries: &VecDeque<Entry>,
+    format: DataFormat,
+) -> color_eyre::Result<String> {
+    let list = ProjectList::new(entries);
+
+    Ok(match format {
+        DataFormat::Json => serde_json::to_string_pretty(&list)?,
+        DataFormat::Toml => toml::to_string_pretty(&list)?,
+    })
+}
+
+/// [`std::fs::canonicalize`] without the `\\?\` prefix windows adds to local paths, which glob
+/// and most programs we pass the path to don't understand
+pub fn canonicalize(path: &Path) -> std::io::Result<PathBuf> {
+    let canonical = std::fs::canonicalize(path)?;
+
+    if cfg!(windows) {
+        let lossy = canonical.to_string_lossy();
+        if let Some(local) = lossy.strip_prefix(r"\\?\") {
+            if !local.starts_with(r"UNC\") {
+                return Ok(PathBuf::from(local));
+            }
+        }
+    }
+
+    Ok(canonical)
+}
+
+/// expands `~` and environment variables like `$HOME` or `${XDG_DATA_HOME}`, failing if a
+/// variable isn't set
+pub fn expand_path(path: &Path) -> color_eyre::Result<PathBuf> {
+    let path = path
+        .to_str()
+        .ok_or(eyre!("path '{path:?}' is not valid utf-8"))?;
+
+    Ok(PathBuf::from(
+        shellexpand::full(path)
+            .map_err(|err| {
+                eyre!(
+                    "unable to expand `{path}`: ${}: {}",
+                    err.var_name,
+                    err.cause
+                )
+            })?
+            .as_ref(),
+    ))
+}
+
+/// canonicalizes the (expanded) path if it exists, otherwise it only gets rid of `.` components
+/// so that `./foo` and `foo` are still considered the same
+pub fn normalized_path(path: &Path) -> PathBuf {
+    let path = &expand_path(path).unwrap_or_else(|_| path.to_path_buf());
+    canonicalize(path).unwrap_or_else(|_| {
+        path.components()
+            .filter(|component| !matches!(component, Component::CurDir))
+            .collect()
+    })
+}
+
+/// removes the entries at `indices` of the deque (not of its internal buffer), in any order and
+/// possibly repeated, returning them in their order in the list
+pub fn remove_entries(entries: &mut VecDeque<Entry>, indices: &[usize]) -> Vec<Entry> {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+
+    // back to front, so the indices still to go don't shift
+    let mut removed: Vec<Entry> = indices
+        .iter()
+        .rev()
+        .filter_map(|idx| entries.remove(*idx))
+        .collect();
+    removed.reverse();
+
+    removed
+}
+
+/// collapses entries with the same normalized path into the first one, returning how many were
+/// removed
+pub fn dedupe_entries(entries: &mut VecDeque<Entry>) -> usize {
+    let before = entries.len();
+    let mut deduped: VecDeque<Entry> = VecDeque::with_capacity(entries.len());
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+
+    for entry in entries.drain(..) {
+        match seen.entry(normalized_path(&entry.path)) {
+            hash_map::Entry::Occupied(first) => deduped[*first.get()].merge(entry),
+            hash_map::Entry::Vacant(slot) => {
+                slot.insert(deduped.len());
+                deduped.push_back(entry);
+            }
+        }
     }
+
+    *entries = deduped;
+    before - entries.len()
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
 }
 
-pub fn generate_expanded_entries(entries: VecDeque<Entry>) -> color_eyre::Result<Vec<Entry>> {
+/// number of leading components of the pattern that don't contain any glob syntax
+fn fixed_prefix_len(pattern: &Path) -> usize {
+    pattern
+        .components()
+        .take_while(|component| !is_glob_pattern(Path::new(component.as_os_str())))
+        .count()
+}
+
+pub fn is_glob_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// expands the glob patterns of all entries, dropping any paths that were already seen, returns
+/// the expanded entries and warnings for the caller to print
+///
+/// entries whose pattern can't be expanded are skipped with a warning, so a single broken entry
+/// doesn't make all the others unavailable
+///
+/// with a `frecency_half_life` the result is ordered by [`Entry::frecency_score`], otherwise
+/// (and between entries with the same score) the stored order is kept
+///
+/// archived entries are left out unless `include_archived` is set
+///
+/// patterns are looked up in and added to the `cache` if one is given
+///
+/// a pattern expanding to more than `warn_above` paths is warned about, as that is more likely
+/// an accident like `~/**` than the list of projects it was meant to be
+pub fn expand(
+    entries: &VecDeque<Entry>,
+    frecency_half_life: Option<f64>,
+    include_archived: bool,
+    mut cache: Option<&mut GlobCache>,
+    warn_above: Option<usize>,
+) -> (Vec<Entry>, Vec<String>) {
+    // the cache is only read up front and written afterwards, so that the patterns which have to
+    // be walked can be expanded concurrently
+    let jobs: Vec<(&Entry, Option<&[PathBuf]>)> = entries
+        .iter()
+        .map(|entry| {
+            let cached = cache.as_deref().zip(cache_key(entry));
+            (
+                entry,
+                cached.and_then(|(cache, (pattern, root))| cache.get(&pattern, &root)),
+            )
+        })
+        .collect();
+    let results = parallel_map(&jobs, |(entry, cached)| {
+        let mut warnings = Vec::new();
+        if entry.archived && !include_archived {
+            return (Ok((Vec::new(), None)), warnings);
+        }
+        (matching_paths(entry, *cached, &mut warnings), warnings)
+    });
+
     let mut res = Vec::with_capacity(entries.len());
+    let mut warnings = Vec::new();
 
     let mut seen_paths = HashSet::new();
 
-    for entry in entries {
-        let path = entry
-            .0
-            .to_str()
-            .ok_or(eyre!("path '{:?}' is not valid utf-8", entry.0))?;
-        let paths = glob::glob(path)?;
+    // merging in the stored order keeps the result the same no matter which walk finished first
+    for (idx, (entry, (result, entry_warnings))) in entries.iter().zip(results).enumerate() {
+        warnings.extend(entry_warnings);
+        let (paths, walked) = match result {
+            Ok(matches) => matches,
+            Err(err) => {
+                warnings.push(format!("skipping {}: {err:#}", entry.path.display()));
+                continue;
+            }
+        };
+
+        if let (Some(cache), Some((pattern, root)), Some(walked)) =
+            (cache.as_deref_mut(), cache_key(entry), walked)
+        {
+            cache.insert(&pattern, &root, walked);
+        }
 
-        for path in paths.filter_map(Result::ok) {
+        if let Some(max) = warn_above.filter(|max| paths.len() > *max) {
+            warnings.push(format!(
+                "{} matches {} paths, more than the {max} expected of a project list (see \
+                 `max_pattern_matches` in the config)",
+                entry.path.display(),
+                paths.len()
+            ));
+        }
+
+        for path in paths {
             if seen_paths.insert(path.clone()) {
-                let entry = entry.clone().with_path(path);
-                res.push(entry);
+                let mut expanded = entry.clone().with_path(path);
+                expanded.source = Some(idx);
+                expanded.pattern = entry.is_pattern().then(|| entry.path.clone());
+                res.push(expanded);
+            }
+        }
+    }
+
+    if let Some(half_life) = frecency_half_life {
+        // scored once up front, the time passing while sorting mustn't change the order
+        let now = unix_now();
+        let mut scored: Vec<(f64, Entry)> = res
+            .into_iter()
+            .map(|entry| (entry.frecency_score(half_life, now), entry))
+            .collect();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        res = scored.into_iter().map(|(_, entry)| entry).collect();
+    }
+
+    (res, warnings)
+}
+
+/// gitignore style file in the fixed part of a pattern, excluding paths the pattern matches
+const IGNORE_FILENAME: &str = ".open-project-ignore";
+
+/// the pattern and the directory it starts in, `None` if the entry can't be cached
+///
+/// relative patterns depend on the directory we're run from, so they aren't
+fn cache_key(entry: &Entry) -> Option<(String, PathBuf)> {
+    let path = entry.expanded_path().ok()?;
+    if !entry.is_pattern() || !path.is_absolute() {
+        return None;
+    }
+
+    let root = path.components().take(fixed_prefix_len(&path)).collect();
+    Some((path.to_str()?.to_string(), root))
+}
+
+/// the paths the entry's pattern expands to, without the excluded ones, those deeper than its
+/// `max_depth` and those in the [ignore file](IGNORE_FILENAME) of the pattern
+///
+/// the pattern is only walked if there are no `cached` paths, in which case everything it
+/// matched is returned as well
+fn matching_paths(
+    entry: &Entry,
+    cached: Option<&[PathBuf]>,
+    warnings: &mut Vec<String>,
+) -> color_eyre::Result<(Vec<PathBuf>, Option<Vec<PathBuf>>)> {
+    // there's no way to check what exists on the other host
+    if entry.remote().is_some() {
+        return Ok((vec![entry.path.clone()], None));
+    }
+
+    let path = entry.expanded_path()?;
+
+    let fixed_components = fixed_prefix_len(&path);
+    let root: PathBuf = path.components().take(fixed_components).collect();
+
+    let (mut paths, walked) = match cached {
+        Some(paths) => (paths.to_vec(), None),
+        None => {
+            let paths = expand_glob(&path.to_string_lossy())?;
+            (paths.clone(), Some(paths))
+        }
+    };
+
+    let exclude = entry
+        .exclude
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .wrap_err_with(|| format!("invalid exclude pattern `{pattern}`"))
+        })
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+
+    let ignore = entry
+        .is_pattern()
+        .then(|| ignore_file(&root, warnings))
+        .flatten();
+
+    paths.retain(|path| {
+        if exclude.iter().any(|pattern| pattern.matches_path(path)) {
+            return false;
+        }
+
+        if let Some(ignore) = &ignore {
+            // glob keeps the pattern's text like `./` while the root is normalized, the ignore
+            // file needs the path spelled like its root
+            let Ok(relative) = path.strip_prefix(&root) else {
+                return false;
+            };
+            if ignore
+                .matched_path_or_any_parents(root.join(relative), path.is_dir())
+                .is_ignore()
+            {
+                return false;
             }
         }
+
+        // `is_dir` follows symlinks, so links to directories are kept as well
+        if !entry.allow_files && !path.is_dir() {
+            return false;
+        }
+
+        entry.max_depth.is_none_or(|max_depth| {
+            path.components().count().saturating_sub(fixed_components) <= max_depth
+        })
+    });
+
+    Ok((paths, walked))
+}
+
+/// all paths matching the pattern, sorted since the order glob yields them in depends on the
+/// filesystem and the picker should be stable between runs
+fn expand_glob(pattern: &str) -> color_eyre::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = glob::glob(pattern)?.filter_map(Result::ok).collect();
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// the matcher for the ignore file in `root`, `None` if there is no such file
+fn ignore_file(root: &Path, warnings: &mut Vec<String>) -> Option<Gitignore> {
+    let path = root.join(IGNORE_FILENAME);
+    if !path.is_file() {
+        return None;
     }
 
-    Ok(res)
+    // invalid lines are skipped, the rest of the file still applies
+    let (ignore, err) = Gitignore::new(&path);
+    if let Some(err) = err {
+        warnings.push(format!("{}: {err}", path.display()));
+    }
+
+    Some(ignore)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the paths the entries expand to, without a cache, frecency or warnings
+    fn expanded_paths(entries: impl IntoIterator<Item = Entry>) -> Vec<PathBuf> {
+        expand(&entries.into_iter().collect(), None, false, None, None)
+            .0
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect()
+    }
+
+    #[test]
+    fn expands_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["delta", "alpha", "charlie", "bravo"] {
+            std::fs::create_dir(dir.path().join(name)).unwrap();
+        }
+
+        assert_eq!(
+            expanded_paths([Entry::new(dir.path().join("*"))]),
+            ["alpha", "bravo", "charlie", "delta"].map(|name| dir.path().join(name))
+        );
+    }
+
+    #[test]
+    fn migrates_bare_array() {
+        let (entries, outdated) =
+            migrate_entries(r#"["/code/a", {"path": "/code/b"}]"#, DataFormat::Json).unwrap();
+
+        assert!(outdated);
+        assert_eq!(
+            entries,
+            [Entry::new("/code/a".into()), Entry::new("/code/b".into())]
+        );
+    }
+
+    #[test]
+    fn reads_current_version() {
+        let content = r#"{"version": 2, "entries": [{"path": "/code/a", "pinned": true}]}"#;
+        let (entries, outdated) = migrate_entries(content, DataFormat::Json).unwrap();
+
+        assert!(!outdated);
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].pinned);
+    }
+
+    #[test]
+    fn defaults_missing_fields() {
+        let content = r#"{"version": 1, "entries": [{"path": "/code/a", "last_opened": 5}]}"#;
+        let (entries, outdated) = migrate_entries(content, DataFormat::Json).unwrap();
+
+        assert!(outdated);
+        assert_eq!(
+            entries[0],
+            Entry {
+                last_opened: Some(5),
+                ..Entry::new("/code/a".into())
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let content = r#"{"version": 3, "entries": []}"#;
+
+        assert!(migrate_entries(content, DataFormat::Json).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn expands_to_directories_only() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("project")).unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("project"), dir.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("README.md"), dir.path().join("notes")).unwrap();
+
+        assert_eq!(
+            expanded_paths([Entry::new(dir.path().join("*"))]),
+            ["linked", "project"].map(|name| dir.path().join(name))
+        );
+        assert_eq!(
+            expanded_paths([Entry {
+                allow_files: true,
+                ..Entry::new(dir.path().join("*"))
+            }]),
+            ["README.md", "linked", "notes", "project"].map(|name| dir.path().join(name))
+        );
+    }
+
+    #[test]
+    fn applies_ignore_file_to_unnormalized_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let code = dir.path().join("code");
+        for name in ["kept", "ignored"] {
+            std::fs::create_dir_all(code.join(name)).unwrap();
+        }
+        std::fs::write(code.join(IGNORE_FILENAME), "ignored\n").unwrap();
+
+        assert_eq!(
+            expanded_paths([Entry::new(dir.path().join("./code/*"))]),
+            [dir.path().join("./code/kept")]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("project")).unwrap();
+        let malformed = dir.path().join("[*");
+        assert!(expand_glob(&malformed.to_string_lossy()).is_err());
+
+        assert_eq!(
+            expanded_paths([Entry::new(malformed), Entry::new(dir.path().join("*")),]),
+            [dir.path().join("project")]
+        );
+    }
+
+    /// run with `cargo test --release -- --ignored --nocapture bench`, the gain depends on the
+    /// number of cores and on how much of the walk has to wait for the disk
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_parallel_expansion() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries: Vec<Entry> = (0..8)
+            .map(|tree| {
+                let root = dir.path().join(format!("tree{tree}"));
+                for outer in 0..40 {
+                    for inner in 0..40 {
+                        std::fs::create_dir_all(root.join(format!("{outer}/{inner}"))).unwrap();
+                    }
+                }
+                Entry::new(root.join("*/*"))
+            })
+            .collect();
+
+        let count = |results: Vec<color_eyre::Result<(Vec<PathBuf>, _)>>| -> usize {
+            results
+                .into_iter()
+                .map(|result| result.unwrap().0.len())
+                .sum()
+        };
+
+        let start = std::time::Instant::now();
+        let sequential = count(
+            entries
+                .iter()
+                .map(|entry| matching_paths(entry, None, &mut Vec::new()))
+                .collect(),
+        );
+        let sequential_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let parallel = count(parallel_map(&entries, |entry| {
+            matching_paths(entry, None, &mut Vec::new())
+        }));
+        let parallel_time = start.elapsed();
+
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        println!(
+            "{sequential} paths, sequential: {sequential_time:?}, parallel: {parallel_time:?} \
+             ({cores} cores)"
+        );
+        assert_eq!(parallel, sequential);
+    }
+
+    const DAY: u64 = 86_400;
+
+    fn opened(open_count: u32, days_ago: u64, now: u64) -> Entry {
+        Entry {
+            open_count,
+            last_opened: Some(now - days_ago * DAY),
+            ..Entry::new("/code/a".into())
+        }
+    }
+
+    #[test]
+    fn recent_use_outranks_old_use() {
+        let now = 1000 * DAY;
+        let old = opened(300, 365, now);
+        let current = opened(7, 1, now);
+
+        assert!(current.frecency_score(14.0, now) > old.frecency_score(14.0, now));
+    }
+
+    #[test]
+    fn frequent_use_outranks_rare_use_at_the_same_time() {
+        let now = 1000 * DAY;
+
+        assert!(
+            opened(10, 3, now).frecency_score(14.0, now)
+                > opened(2, 3, now).frecency_score(14.0, now)
+        );
+    }
+
+    #[test]
+    fn score_halves_every_half_life() {
+        let now = 1000 * DAY;
+        let fresh = opened(8, 0, now).frecency_score(7.0, now);
+
+        assert_eq!(opened(8, 7, now).frecency_score(7.0, now), fresh / 2.0);
+        assert_eq!(opened(8, 14, now).frecency_score(7.0, now), fresh / 4.0);
+    }
+
+    #[test]
+    fn infinite_half_life_only_counts_opens() {
+        let now = 1000 * DAY;
+        let old = opened(300, 365, now);
+        let current = opened(7, 1, now);
+
+        assert!(
+            old.frecency_score(f64::INFINITY, now) > current.frecency_score(f64::INFINITY, now)
+        );
+    }
+
+    #[test]
+    fn never_opened_scores_zero() {
+        assert_eq!(Entry::new("/code/a".into()).frecency_score(14.0, DAY), 0.0);
+    }
+
+    #[test]
+    fn removes_selected_entries_of_rotated_list() {
+        let labels = |entries: &VecDeque<Entry>| -> Vec<String> {
+            entries.iter().map(Entry::label).collect()
+        };
+        let mut entries: VecDeque<Entry> = ["c", "d", "e"]
+            .into_iter()
+            .map(|name| Entry::new(name.into()))
+            .collect();
+        // wraps around the end of the buffer, so its slices no longer match the list order
+        entries.push_front(Entry::new("b".into()));
+        entries.push_front(Entry::new("a".into()));
+        entries.rotate_left(1);
+        assert_eq!(labels(&entries), ["b", "c", "d", "e", "a"]);
+
+        let removed = remove_entries(&mut entries, &[4, 1, 1]);
+
+        assert_eq!(labels(&removed.into()), ["c", "a"]);
+        assert_eq!(labels(&entries), ["b", "d", "e"]);
+    }
+
+    #[test]
+    fn round_trips_toml() {
+        let entries = VecDeque::from([
+            Entry {
+                tags: vec!["work".to_string()],
+                backend: Some(BackendKind::Editor),
+                open_count: 3,
+                ..Entry::new("/code/a".into())
+            },
+            Entry::new("~/code/*".into()),
+        ]);
+
+        let content = serialize_entries(&entries, DataFormat::Toml).unwrap();
+        let (read, outdated) = migrate_entries(&content, DataFormat::Toml).unwrap();
+
+        assert!(!outdated);
+        assert_eq!(read, entries);
+    }
 }
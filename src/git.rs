@@ -0,0 +1,69 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::parallel::parallel_map;
+
+pub struct RepoStatus {
+    /// the checked out branch
+    pub branch: String,
+
+    /// whether the working tree has uncommitted changes
+    pub dirty: bool,
+}
+
+/// the status of every path that is a git repository, queried concurrently since every lookup
+/// spawns processes
+pub fn repo_statuses(paths: &[PathBuf]) -> Vec<Option<RepoStatus>> {
+    parallel_map(paths, |path| repo_status(path))
+}
+
+/// the closest directory containing `path` that has a `.git`, which is a file in worktrees
+pub fn git_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+fn repo_status(path: &Path) -> Option<RepoStatus> {
+    if !path.join(".git").exists() {
+        return None;
+    }
+
+    let branch = git_output(path, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let branch = branch.trim().to_string();
+    if branch.is_empty() {
+        return None;
+    }
+
+    let dirty = git_output(path, &["status", "--porcelain"])
+        .is_some_and(|status| !status.trim().is_empty());
+
+    Some(RepoStatus { branch, dirty })
+}
+
+/// stdout of the git command run in `path`, if it succeeded
+fn git_output(path: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+/// the repository name of a clone url, e.g. `bar` for `git@host:foo/bar.git`
+pub fn repo_name(url: &str) -> Option<&str> {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let name = url.rsplit(['/', ':']).next()?;
+
+    (!name.is_empty()).then_some(name)
+}
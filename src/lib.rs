@@ -0,0 +1,231 @@
+//! the logic behind the `open-project` cli, usable by other tools which want to open projects
+//! the same way
+//!
+//! the project list is read with [`load_entries`], its patterns are expanded with [`expand`] and
+//! an expanded entry is opened with [`open`]
+
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    fs::File,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context};
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+
+use crate::{
+    config::DataFormat,
+    entry::{migrate_entries, serialize_entries},
+};
+
+pub mod cache;
+pub mod config;
+pub mod entry;
+pub mod git;
+pub mod open;
+mod parallel;
+pub mod picker;
+pub mod zellij;
+
+pub use entry::{expand, Entry};
+pub use open::{open, Backend, BackendKind, Verbosity};
+
+/// reads the entries in the [format](DataFormat::of) of the file, lists in an older format are
+/// migrated and saved right away
+///
+/// if the file can't be parsed, [`is_syntax_error`] tells whether starting over with
+/// [`reset_entries`] is worth offering
+pub fn load_entries(path: &Path) -> color_eyre::Result<VecDeque<Entry>> {
+    let content = std::fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(VecDeque::new());
+    }
+
+    let (entries, outdated) = migrate_entries(&content, DataFormat::of(path))
+        .wrap_err_with(|| format!("invalid project list at {}", path.display()))?;
+    if outdated {
+        save_entries(path, &entries)?;
+    }
+
+    Ok(entries)
+}
+
+/// whether [`load_entries`] failed because the file isn't valid json or toml, rather than e.g.
+/// because it was written by a newer version, which starting over would lose
+pub fn is_syntax_error(err: &color_eyre::Report) -> bool {
+    err.chain()
+        .any(|cause| cause.is::<serde_json::Error>() || cause.is::<toml::de::Error>())
+}
+
+/// where [`reset_entries`] moves the project list at `path`
+pub fn broken_list_path(path: &Path) -> PathBuf {
+    path.with_extension(match DataFormat::of(path) {
+        DataFormat::Json => "json.bak",
+        DataFormat::Toml => "toml.bak",
+    })
+}
+
+/// moves the project list out of the way to its [`broken_list_path`] and starts over with an
+/// empty one
+pub fn reset_entries(path: &Path) -> color_eyre::Result<VecDeque<Entry>> {
+    std::fs::rename(path, broken_list_path(path))?;
+    let entries = VecDeque::new();
+    save_entries(path, &entries)?;
+
+    Ok(entries)
+}
+
+/// the user backed out of a prompt or picker, which the cli exits on without a report
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// writes the entries in the [format](DataFormat::of) of `path` to a temporary file next to it and
+/// then moves it into place, so that the list isn't lost if we get killed halfway through
+///
+/// the list it replaces is kept as a [backup](restore_backup)
+pub fn save_entries(path: &Path, entries: &VecDeque<Entry>) -> color_eyre::Result<()> {
+    write_entries(path, entries, true)
+}
+
+/// [`save_entries`] without a backup, for recording that entries were opened, which would
+/// otherwise push the changes worth undoing out of the backups
+pub fn save_opened(path: &Path, entries: &VecDeque<Entry>) -> color_eyre::Result<()> {
+    write_entries(path, entries, false)
+}
+
+fn write_entries(path: &Path, entries: &VecDeque<Entry>, backup: bool) -> color_eyre::Result<()> {
+    let mut tmp_name = path
+        .file_name()
+        .ok_or(eyre!("{} is not a file path", path.display()))?
+        .to_os_string();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(serialize_entries(entries, DataFormat::of(path))?.as_bytes())?;
+    file.sync_all()?;
+
+    if backup {
+        rotate_backups(path)?;
+    }
+    std::fs::rename(&tmp_path, path)
+        .wrap_err_with(|| format!("unable to move {} into place", tmp_path.display()))?;
+
+    Ok(())
+}
+
+/// how many previous versions of the project list are kept next to it
+pub const BACKUPS: usize = 5;
+
+/// `path` with `.n` appended, the n-th most recent backup of the project list at `path`
+fn backup_path(path: &Path, n: usize) -> color_eyre::Result<PathBuf> {
+    let mut name = path
+        .file_name()
+        .ok_or(eyre!("{} is not a file path", path.display()))?
+        .to_os_string();
+    name.push(format!(".{n}"));
+
+    Ok(path.with_file_name(name))
+}
+
+/// copies the project list to its first backup, shifting the others back and dropping the oldest
+fn rotate_backups(path: &Path) -> color_eyre::Result<()> {
+    if !path.try_exists()? {
+        return Ok(());
+    }
+
+    for n in (1..BACKUPS).rev() {
+        let backup = backup_path(path, n)?;
+        if backup.try_exists()? {
+            std::fs::rename(&backup, backup_path(path, n + 1)?)?;
+        }
+    }
+
+    let backup = backup_path(path, 1)?;
+    std::fs::copy(path, &backup)
+        .wrap_err_with(|| format!("unable to back up the project list to {}", backup.display()))?;
+
+    Ok(())
+}
+
+/// replaces the project list with its most recent backup, returning whether there was one
+///
+/// the older backups move up, so restoring again goes back further
+pub fn restore_backup(path: &Path) -> color_eyre::Result<bool> {
+    let latest = backup_path(path, 1)?;
+    if !latest.try_exists()? {
+        return Ok(false);
+    }
+
+    std::fs::rename(&latest, path)
+        .wrap_err_with(|| format!("unable to move {} into place", latest.display()))?;
+    for n in 2..=BACKUPS {
+        let backup = backup_path(path, n)?;
+        if !backup.try_exists()? {
+            break;
+        }
+        std::fs::rename(&backup, backup_path(path, n - 1)?)?;
+    }
+
+    Ok(true)
+}
+
+/// whether colors may be written to `stream`, which they can't if it isn't a terminal or
+/// `NO_COLOR` is set
+pub fn should_use_color(stream: &impl IsTerminal) -> bool {
+    color_allowed(
+        std::env::var_os("NO_COLOR").as_deref(),
+        stream.is_terminal(),
+    )
+}
+
+/// `NO_COLOR` only counts if it isn't empty, see <https://no-color.org>
+fn color_allowed(no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+    no_color.is_none_or(|value| value.is_empty()) && is_terminal
+}
+
+/// the theme of the prompts, which are drawn to stderr
+pub fn theme() -> Box<dyn Theme> {
+    if should_use_color(&std::io::stderr()) {
+        Box::new(ColorfulTheme::default())
+    } else {
+        Box::new(SimpleTheme)
+    }
+}
+
+pub fn is_in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_disables_colors() {
+        assert!(!color_allowed(Some(OsStr::new("1")), true));
+        assert!(!color_allowed(Some(OsStr::new("false")), true));
+    }
+
+    #[test]
+    fn empty_no_color_is_ignored() {
+        assert!(color_allowed(Some(OsStr::new("")), true));
+        assert!(color_allowed(None, true));
+    }
+
+    #[test]
+    fn no_colors_without_terminal() {
+        assert!(!color_allowed(None, false));
+    }
+}
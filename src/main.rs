@@ -1,259 +1,2651 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    cmp::Reverse,
+    collections::{HashSet, VecDeque},
     fs::File,
-    io::Write,
+    io::{IsTerminal, Write},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
-    str::FromStr,
+    process::{Command, ExitStatus, Stdio},
 };
 
-use clap::{error::ErrorKind, Parser};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 use color_eyre::eyre::{eyre, Context};
-use dialoguer::{theme::ColorfulTheme, FuzzySelect, MultiSelect};
-use entry::Entry;
-
-use crate::entry::generate_expanded_entries;
-
-mod entry;
-
-const DATA_FILENAME: &str = "projects.json";
+use dialoguer::{Confirm, FuzzySelect, MultiSelect, Select};
+use open_project::{
+    broken_list_path,
+    cache::{clear_cache, GlobCache},
+    config::{Config, DataFormat, GuiChooser, Multiplexer, CONFIG_FILENAME},
+    entry::{
+        canonicalize, dedupe_entries, display_path, expand_path, is_glob_pattern, migrate_entries,
+        normalized_path, parse_remote, remove_entries, unix_now, ProjectList,
+    },
+    expand, git, is_in_path, is_syntax_error, load_entries, open,
+    open::{
+        create_detached_tmux_session, is_gui_editor, new_detached_tmux_session, open_tmux_session,
+        preferred_editor, run_command, running_sessions, sanitize_session_name, shell_quote,
+        tmux_session_conflict, tmux_target_session,
+    },
+    picker, reset_entries, restore_backup, save_entries, save_opened, should_use_color, theme,
+    zellij::zellij_sessions,
+    Backend, BackendKind, Cancelled, Entry, Verbosity,
+};
 
 /// Cli to open projects easily easily without needing to care for the working directory
 /// currently: open a new wezterm tab and open `zellij -l=<layout>` inside it
 #[derive(Parser, Debug)]
-enum Cli {
-    Open,
+struct Cli {
+    /// project list to use instead of the one in the data directory
+    #[clap(long, global = true, env = "OPEN_PROJECT_DATA_FILE")]
+    data_file: Option<PathBuf>,
+
+    /// expand patterns without the cache of previous expansions
+    #[clap(long, global = true)]
+    no_cache: bool,
+
+    /// print what is being run, can be given multiple times
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// don't report failing commands
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// start sessions in the git repository the project is in, see `cwd_from_git_root` in the
+    /// config
+    #[clap(long, global = true)]
+    cwd_from_git_root: bool,
+
+    /// how to report whether the command succeeded, `json` prints `{"status":"ok"}`,
+    /// `{"status":"cancelled"}` or `{"status":"error","message":...}` to stderr for scripts
+    #[clap(long, global = true, value_enum, default_value_t)]
+    output: OutputFormat,
+
+    /// defaults to `open`
+    #[clap(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    Open {
+        /// how to open the project instead of the entry's backend or the configured multiplexer in
+        /// the current terminal
+        #[clap(short, long, value_enum)]
+        backend: Option<BackendKind>,
+
+        /// open a new window for the terminal backend and gui editors
+        #[clap(short, long)]
+        new_window: bool,
+
+        /// only create the tmux session without attaching to it and print its name
+        #[clap(short, long)]
+        detach: bool,
+
+        /// start a new tmux session even if there already is one for the project
+        #[clap(long)]
+        new_session: bool,
+
+        /// pick from the paths (or patterns) read from stdin, one per line, instead of the project
+        /// list, which is left untouched
+        #[clap(long)]
+        stdin: bool,
+
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
     OpenTerm {
         #[clap(short, long)]
         new_window: bool,
+
+        /// start a new tmux session even if there already is one for the project
+        #[clap(long)]
+        new_session: bool,
+
+        #[clap(flatten)]
+        picker: PickerArgs,
     },
+    /// open the project in a new tab of the terminal, picked with a gui chooser
+    ///
+    /// a query opens its match directly instead, for launchers which already know the project,
+    /// failing if it matches several entries
     OpenGui {
         #[clap(short, long)]
         new_window: bool,
+
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
+    /// open the project in `$EDITOR`
+    OpenEditor {
+        /// open a new window for gui editors
+        #[clap(short, long)]
+        new_window: bool,
+
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
+    /// open the project in a zellij session, regardless of the configured multiplexer
+    OpenZellij {
+        /// print the zellij sessions as json instead of opening anything
+        #[clap(long)]
+        list_sessions: bool,
+
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
+    /// open the project in the file manager
+    OpenFiles {
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
+    /// select several projects and create a tmux session for each, attaching to one of them
+    ///
+    /// the entries are always selected with a checklist, the picker options only filter them
+    OpenMany {
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
+    /// only print the path of the selected project, e.g. for `cd "$(open-project pick)"`
+    Pick {
+        #[clap(flatten)]
+        picker: PickerArgs,
+    },
+    List {
+        #[clap(short, long, value_enum, default_value_t)]
+        format: ListFormat,
+
+        /// list the paths the patterns expand to instead of the stored entries
+        #[clap(short, long)]
+        expand: bool,
+
+        /// order of the entries, defaults to the order they are stored in
+        #[clap(short, long, value_enum)]
+        sort: Option<SortKey>,
+
+        /// also list archived entries
+        #[clap(short, long)]
+        all: bool,
+    },
+    /// print the completion script for a shell
+    Completions { shell: Shell },
+    /// print a shell function `op` which changes into the picked project, for
+    /// `eval "$(open-project init zsh)"`
+    Init { shell: InitShell },
+    /// print all entries containing the query, one per line
+    Search {
+        query: String,
+
+        /// search the stored entries instead of the paths their patterns expand to
+        #[clap(long = "no-expand", action = ArgAction::SetFalse)]
+        expand: bool,
+    },
+    /// print a summary of the project list
+    Stats,
+    /// list entries by the time they were last opened
+    Recent {
+        /// how many entries to show
+        limit: Option<usize>,
+    },
+    /// open the most recently opened project without a picker, like `open` does
+    Last {
+        /// open the n-th most recent one instead, 1 being the most recent
+        #[clap(default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        n: u64,
+
+        /// print the commands that would be run instead of running them
+        #[clap(long)]
+        dry_run: bool,
     },
-    List,
     Add {
-        path: PathBuf,
+        /// browse for a directory interactively if none are given
+        paths: Vec<PathBuf>,
+
+        /// name of the tmux session, defaults to the last path component
+        #[clap(short, long)]
+        name: Option<String>,
+
+        /// shown next to the path in the pickers
+        #[clap(short, long)]
+        description: Option<String>,
+
+        /// tag the entry, can be given multiple times
+        #[clap(short, long = "tag", alias = "add-tag")]
+        tags: Vec<String>,
+
+        /// skip expanded paths matching this glob pattern (e.g. `**/node_modules`), can be given
+        /// multiple times
+        #[clap(short, long)]
+        exclude: Vec<String>,
+
+        /// how deep below the first wildcard of the pattern paths may be
+        #[clap(long)]
+        max_depth: Option<usize>,
+
+        /// keep files the pattern matches instead of only directories
+        #[clap(long)]
+        allow_files: bool,
+
+        /// shell command to run when a new session is created for the entry
+        #[clap(short, long)]
+        run: Option<String>,
+
+        /// directory to start sessions in instead of the path, relative to the path
+        #[clap(long)]
+        cwd: Option<PathBuf>,
+
+        /// name of a window to create in new tmux sessions, can be given multiple times
+        #[clap(short, long = "window")]
+        windows: Vec<String>,
+
+        /// bookmark a file relative to the path, `open-editor` offers to open it instead of the
+        /// path, can be given multiple times
+        #[clap(short, long = "file")]
+        files: Vec<PathBuf>,
+
+        /// how `open` opens the entry, instead of the configured multiplexer
+        #[clap(short, long, value_enum)]
+        backend: Option<BackendKind>,
+
+        /// add it to the start of the list, giving it a higher priority
+        #[clap(short, long)]
+        prepend: bool,
+    },
+    /// clone a git repository into `projects_root` from the config and add it
+    Clone {
+        url: String,
+
+        /// directory to clone into instead of one named after the repository in `projects_root`
+        into: Option<PathBuf>,
 
+        /// open the project after adding it
+        #[clap(short, long)]
+        open: bool,
+    },
+    /// add the current directory
+    Here {
         /// add it to the start of the list, giving it a higher priority
         #[clap(short, long)]
         prepend: bool,
     },
     Remove {
         path: Option<PathBuf>,
+
+        /// don't ask before removing the entries
+        #[clap(short, long)]
+        yes: bool,
+    },
+    /// move an entry to another position in the list, interactively if no arguments are given
+    Move {
+        #[clap(requires = "to")]
+        path: Option<PathBuf>,
+
+        /// new index of the entry, 0 being the start of the list
+        to: Option<usize>,
+    },
+    /// change the path of an entry, keeping its position and metadata
+    Rename { old: PathBuf, new: PathBuf },
+    /// always list an entry first in the pickers
+    Pin { path: PathBuf },
+    /// undo `pin`
+    Unpin { path: PathBuf },
+    /// hide an entry without removing it, `list --all` still shows it
+    Archive { path: PathBuf },
+    /// undo `archive`
+    Unarchive { path: PathBuf },
+    /// open the project list in `$EDITOR`
+    Edit,
+    /// restore the project list from before the last change, opening a project doesn't count,
+    /// can be repeated to go back further
+    Undo,
+    /// merge entries pointing to the same path
+    Dedupe,
+    /// remove the cache of what patterns expanded to, which is only refreshed when the directory
+    /// a pattern starts in changes
+    ClearCache,
+    /// check the project list and config for problems, failing if there are errors
+    Validate,
+    /// check which programs are installed and how the environment is set up
+    Doctor,
+    /// write the project list as json to a file or stdout
+    Export {
+        /// defaults to stdout
+        path: Option<PathBuf>,
+    },
+    /// add the directories known to zoxide
+    ImportZoxide {
+        /// skip directories with a lower zoxide score
+        #[clap(long)]
+        min_score: Option<f64>,
+    },
+    /// replace the project list with one previously exported
+    Import {
+        path: PathBuf,
+
+        /// add the imported entries to the current ones instead of replacing them
+        #[clap(short, long)]
+        merge: bool,
     },
+    /// remove entries whose path doesn't exist anymore
+    Prune {
+        /// only print what would be removed
+        #[clap(short = 'n', long)]
+        dry_run: bool,
+    },
+}
+
+// options shared by all commands that let the user pick an entry
+#[derive(Args, Debug, Default)]
+struct PickerArgs {
+    /// open the entry matching this directly (by path, name or substring), only showing the
+    /// picker if there are multiple matches
+    query: Option<String>,
+
+    /// only show entries with this tag
+    #[clap(short, long)]
+    tag: Option<String>,
+
+    /// keep the stored order instead of sorting by how often and recently entries were opened
+    #[clap(long)]
+    no_frecency: bool,
+
+    /// also show archived entries
+    #[clap(long)]
+    include_archived: bool,
+
+    /// only show the first entries, after sorting and filtering
+    #[clap(long)]
+    limit: Option<usize>,
+
+    /// keep the paths expanded from the same pattern together, prefixed with the pattern
+    #[clap(short, long)]
+    group: bool,
+
+    /// picker used in the terminal, `auto` uses fzf (with a preview of the directory) if it's
+    /// installed, the builtin one also matches the tags and ctrl-x removes the highlighted entry
+    /// from the list
+    #[clap(long, value_enum, default_value_t)]
+    picker: PickerKind,
+
+    /// show the checked out branch of git repositories, marked with a `*` if there are uncommitted
+    /// changes
+    #[clap(long)]
+    show_git: bool,
+
+    /// print the commands that would be run instead of running them
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum SortKey {
+    /// by the session name, ignoring case
+    Name,
+    /// by the path, ignoring case
+    Path,
+    /// the most recently opened first
+    Recent,
+    /// the most often opened first
+    Frequency,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum InitShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum ListFormat {
+    /// one entry per line as shown in the picker
+    Plain,
+    #[default]
+    Json,
+    /// only the paths, one per line
+    Paths,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum OutputFormat {
+    /// color-eyre's report on errors
+    #[default]
+    Human,
+    /// one json object with the status on stderr
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum PickerKind {
+    #[default]
+    Auto,
+    Fzf,
+    Builtin,
 }
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
-    let cli = match Cli::try_parse() {
-        Ok(cli) => cli,
-        Err(err) => match err.kind() {
-            ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => Cli::Open,
-            _ => {
+    let cli = Cli::try_parse().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+
+    match cli.output {
+        OutputFormat::Human => match run(cli) {
+            Err(err) if err.is::<Cancelled>() => std::process::exit(1),
+            Err(err) if err.is::<Failure>() => {
                 eprintln!("{err}");
                 std::process::exit(1);
             }
+            result => result,
         },
-    };
+        OutputFormat::Json => {
+            let result = run(cli);
+            let status = match &result {
+                Ok(()) => serde_json::json!({ "status": "ok" }),
+                Err(err) if err.is::<Cancelled>() => serde_json::json!({ "status": "cancelled" }),
+                Err(err) => serde_json::json!({
+                    "status": "error",
+                    "message": err.to_string(),
+                    "causes": err.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+                }),
+            };
+            eprintln!("{status}");
+            if result.is_err() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// an expected failure like a query without matches, reported as a plain message instead of
+/// color-eyre's report
+#[derive(Debug)]
+struct Failure(String);
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+fn run(cli: Cli) -> color_eyre::Result<()> {
+    let verbosity = Verbosity::from_flags(cli.verbose, cli.quiet);
+    let action = cli.action.unwrap_or(Action::Open {
+        backend: None,
+        new_window: false,
+        detach: false,
+        new_session: false,
+        stdin: false,
+        picker: PickerArgs::default(),
+    });
+
+    if let Action::Completions { shell } = action {
+        let mut command = Cli::command();
+        let bin_name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, bin_name, &mut std::io::stdout());
+        return Ok(());
+    }
+
+    if let Action::Init { shell } = action {
+        print!("{}", init_script(shell));
+        return Ok(());
+    }
 
     let project_dirs = directories::ProjectDirs::from("", "", "open-project-cli")
         .ok_or(eyre!("unable to valid home directory path"))?;
-    let entries_filepath = project_dirs.data_dir().join(DATA_FILENAME);
+    let config_path = project_dirs.config_dir().join(CONFIG_FILENAME);
+    let default_data_file = cli.data_file.is_none();
+    let entries_filepath = match cli.data_file {
+        Some(path) => path,
+        None => default_data_file_path(project_dirs.data_dir(), &config_path)?,
+    };
+
+    // have to run before anything is loaded, as finding broken files is the point of them, and
+    // before a missing list is created so they only look at what's there
+    if let Action::Validate = action {
+        if !validate(&entries_filepath, &config_path)? {
+            return Err(Failure("the project list or config is invalid".to_string()).into());
+        }
+        return Ok(());
+    }
+    if let Action::Doctor = action {
+        if !doctor(&entries_filepath, &config_path) {
+            return Err(Failure("some checks failed".to_string()).into());
+        }
+        return Ok(());
+    }
+
+    let reads_stdin = matches!(action, Action::Open { stdin: true, .. });
+
+    if !reads_stdin && !entries_filepath.try_exists()? {
+        if let Some(parent) = entries_filepath.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
-    if !entries_filepath.try_exists()? {
-        std::fs::create_dir_all(
-            entries_filepath
-                .parent()
-                .expect("should have a valid data directory"),
-        )?;
-        std::fs::write(&entries_filepath, "[]")?;
+        // switching the configured format to toml takes the entries along
+        let json_path = entries_filepath.with_file_name(DataFormat::Json.filename());
+        let entries = if default_data_file
+            && DataFormat::of(&entries_filepath) == DataFormat::Toml
+            && json_path.try_exists()?
+        {
+            eprintln!(
+                "copying the entries of {} to {}",
+                json_path.display(),
+                entries_filepath.display()
+            );
+            load_entries(&json_path)?
+        } else {
+            VecDeque::new()
+        };
+        save_entries(&entries_filepath, &entries)?;
     }
 
-    let mut entries: VecDeque<Entry> = serde_json::from_reader(File::open(&entries_filepath)?)?;
+    let mut config = Config::load(&config_path)?;
+    config.cwd_from_git_root |= cli.cwd_from_git_root;
 
-    match cli {
-        Cli::Open => {
-            let entries = generate_expanded_entries(entries)?;
+    // editing has to work even if the file can't be parsed
+    if let Action::Edit = action {
+        let editor = preferred_editor(&config);
+        let mut words = editor.split_whitespace();
+        let mut command = Command::new(words.next().unwrap_or("nvim"));
+        command.args(words).arg(&entries_filepath);
+        run_command(&mut command, "editor failed", verbosity, false)?;
 
-            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-                .items(&entries)
-                .interact_opt()?
-                .unwrap_or_else(|| std::process::exit(1));
+        let content = std::fs::read_to_string(&entries_filepath)?;
+        if !content.trim().is_empty() {
+            if let Err(err) = migrate_entries(&content, DataFormat::of(&entries_filepath)) {
+                return Err(Failure(format!(
+                    "{} won't load until this is fixed: {err:#}",
+                    entries_filepath.display()
+                ))
+                .into());
+            }
+        }
+
+        return Ok(());
+    }
+
+    // the backup replaces the list, which might not even load anymore
+    if let Action::Undo = action {
+        if !restore_backup(&entries_filepath)? {
+            return Err(
+                Failure("there is no earlier version of the project list".to_string()).into(),
+            );
+        }
+        println!("restored the project list from before the last change");
+
+        return Ok(());
+    }
+
+    let mut entries = if reads_stdin {
+        read_stdin_entries()?
+    } else {
+        load_or_reset_entries(&entries_filepath)?
+    };
 
-            let selected_entry = &entries[selection];
+    let cache_path = entries_filepath.with_extension("cache.json");
+    let mut cache = (!cli.no_cache && !reads_stdin).then(|| GlobCache::load(&cache_path));
+    let warn_above = config.pattern_match_warning();
 
-            open_tmux_session(&selected_entry.0)?;
+    match action {
+        Action::Open {
+            backend,
+            new_window,
+            detach,
+            new_session,
+            stdin,
+            picker,
+        } => {
+            // the flags are checked before picking if they don't depend on the entry
+            let backend = backend
+                .map(|kind| open_backend(Some(kind), new_window, new_session, detach, &config))
+                .transpose()?;
+
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            // the piped in paths aren't stored anywhere they could be removed from
+            let project_list = (!stdin).then_some((&mut entries, entries_filepath.as_path()));
+            let selected_entry = select_entry(&mut expanded, &picker, &config, project_list)?;
+
+            let backend = match backend {
+                Some(backend) => backend,
+                None => open_backend(
+                    selected_entry.backend,
+                    new_window,
+                    new_session,
+                    detach,
+                    &config,
+                )?,
+            };
+
+            if !picker.dry_run && !stdin {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
+
+            if detach {
+                let name = create_detached_tmux_session(
+                    selected_entry,
+                    confirm_new_session(selected_entry, new_session)?,
+                    verbosity,
+                    picker.dry_run,
+                )?;
+                println!("{name}");
+            } else {
+                open_entry(selected_entry, backend, &config, verbosity, picker.dry_run)?;
+            }
 
             Ok(())
         }
-        Cli::OpenTerm { new_window } => {
-            let entries = generate_expanded_entries(entries)?;
+        Action::OpenTerm {
+            new_window,
+            new_session,
+            picker,
+        } => {
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            let selected_entry = select_entry(
+                &mut expanded,
+                &picker,
+                &config,
+                Some((&mut entries, &entries_filepath)),
+            )?;
 
-            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-                .items(&entries)
-                .interact_opt()?
-                .unwrap_or_else(|| std::process::exit(1));
+            if !picker.dry_run {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
+
+            open_entry(
+                selected_entry,
+                Backend::Terminal {
+                    new_window,
+                    new_session,
+                },
+                &config,
+                verbosity,
+                picker.dry_run,
+            )?;
+
+            Ok(())
+        }
+        Action::OpenEditor { new_window, picker } => {
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            let selected_entry = select_entry(
+                &mut expanded,
+                &picker,
+                &config,
+                Some((&mut entries, &entries_filepath)),
+            )?;
 
-            let selected_entry = &entries[selection];
+            if !picker.dry_run {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
 
-            wezterm_open_path_in_tab(&selected_entry.0, new_window)?;
+            open_entry(
+                selected_entry,
+                Backend::Editor {
+                    new_window,
+                    file: None,
+                },
+                &config,
+                verbosity,
+                picker.dry_run,
+            )?;
 
             Ok(())
         }
-        Cli::OpenGui { new_window } => {
-            let entries: HashMap<String, Entry> = generate_expanded_entries(entries)?
-                .into_iter()
-                .map(|entry| (entry.to_string(), entry))
-                .collect();
+        Action::OpenZellij {
+            list_sessions,
+            picker,
+        } => {
+            if list_sessions {
+                println!("{}", serde_json::to_string_pretty(&zellij_sessions()?)?);
+                return Ok(());
+            }
 
-            let mut chooser = if cfg!(target_os = "linux") {
-                let mut anyrun = Command::new("anyrun");
-                anyrun.args([
-                    "--plugins",
-                    "libstdin.so",
-                    "--show-results-immediately",
-                    "true",
-                ]);
-                anyrun
-            } else if cfg!(target_os = "macos") {
-                Command::new("choose")
-            } else {
-                panic!("unsupported os");
-            };
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            let selected_entry = select_entry(
+                &mut expanded,
+                &picker,
+                &config,
+                Some((&mut entries, &entries_filepath)),
+            )?;
+
+            if !picker.dry_run {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
 
-            let mut chooser = chooser
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .spawn()?;
+            open_entry(
+                selected_entry,
+                Backend::Zellij,
+                &config,
+                verbosity,
+                picker.dry_run,
+            )?;
 
-            let mut chooser_stdin = chooser
-                .stdin
-                .take()
-                .expect("should be able to take stdin of rofi");
+            Ok(())
+        }
+        Action::OpenFiles { picker } => {
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            let selected_entry = select_entry(
+                &mut expanded,
+                &picker,
+                &config,
+                Some((&mut entries, &entries_filepath)),
+            )?;
 
-            for entry in &entries {
-                writeln!(chooser_stdin, "{}", entry.0)?;
+            if !picker.dry_run {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
             }
-            drop(chooser_stdin);
 
-            let selected_str = String::from_utf8(chooser.wait_with_output()?.stdout)?;
-            let selected_str = selected_str.trim();
+            open_entry(
+                selected_entry,
+                Backend::Files,
+                &config,
+                verbosity,
+                picker.dry_run,
+            )?;
 
-            if selected_str.is_empty() {
-                std::process::exit(1);
+            Ok(())
+        }
+        Action::OpenMany { picker } => {
+            if config.multiplexer != Multiplexer::Tmux || !cfg!(unix) {
+                return Err(eyre!("open-many only works with tmux as the multiplexer"));
+            }
+
+            let expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            let labels = entry_labels(&expanded, &picker, &config, false);
+
+            let selected = MultiSelect::with_theme(&*theme())
+                .items(&labels)
+                .interact_opt()?
+                .ok_or(Cancelled)?;
+            if selected.is_empty() {
+                println!("nothing selected");
+                return Ok(());
+            }
+
+            let attach = if selected.len() > 1 {
+                let selected_labels: Vec<&String> =
+                    selected.iter().map(|idx| &labels[*idx]).collect();
+                Select::with_theme(&*theme())
+                    .with_prompt("attach to")
+                    .items(&selected_labels)
+                    .default(0)
+                    .interact_opt()?
+                    .ok_or(Cancelled)?
+            } else {
+                0
+            };
+
+            // only the session attached to at the end may block, the others are created detached
+            for (pos, idx) in selected.iter().enumerate() {
+                if pos == attach {
+                    continue;
+                }
+
+                let entry = &expanded[*idx];
+                let new_session = confirm_new_session(entry, false)?;
+                let Some((name, exists)) = tmux_target_session(entry, new_session, verbosity)?
+                else {
+                    continue;
+                };
+                if exists {
+                    println!("tmux session {name} already exists");
+                } else if new_detached_tmux_session(
+                    &name,
+                    entry,
+                    entry.run.as_deref(),
+                    verbosity,
+                    picker.dry_run,
+                )? {
+                    println!("created tmux session {name}");
+                }
             }
 
-            let selected_entry = entries
-                .get(selected_str)
-                .ok_or(eyre!("unknown entry (`{selected_str}`) got selected"))?;
+            if !picker.dry_run {
+                for idx in &selected {
+                    record_open(&mut entries, &expanded[*idx]);
+                }
+                save_opened(&entries_filepath, &entries)?;
+            }
 
-            wezterm_open_path_in_tab(&selected_entry.0, new_window)?;
+            let attached = &expanded[selected[attach]];
+            open_tmux_session(
+                attached,
+                None,
+                confirm_new_session(attached, false)?,
+                verbosity,
+                picker.dry_run,
+            )?;
 
             Ok(())
         }
-        Cli::List => {
-            println!("{}", serde_json::to_string_pretty(&entries)?);
+        Action::Pick { picker } => {
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
+            // the pickers draw on stderr or the tty, so stdout only gets the path
+            let selected_entry = select_entry(
+                &mut expanded,
+                &picker,
+                &config,
+                Some((&mut entries, &entries_filepath)),
+            )?;
+
+            println!("{}", selected_entry.path.display());
+
+            if !picker.dry_run {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
+
             Ok(())
         }
-        Cli::Add { path, prepend } => {
-            let path = PathBuf::from_str(&shellexpand::tilde(
-                path.to_str().ok_or(eyre!("expected valid utf-8 path"))?,
-            ))?;
+        Action::OpenGui { new_window, picker } => {
+            let mut expanded = picker_entries(
+                &entries, &picker, &config, warn_above, &mut cache, verbosity,
+            )?;
 
-            if prepend {
-                entries.push_front(Entry(path));
+            if let Some(query) = picker.query.as_ref().filter(|_| expanded.len() > 1) {
+                let matches: Vec<String> = expanded
+                    .iter()
+                    .map(|entry| format!("\n  {entry}"))
+                    .collect();
+                return Err(eyre!(
+                    "`{query}` matches {} entries, be more specific:{}",
+                    expanded.len(),
+                    matches.concat()
+                ));
+            }
+
+            // there are no stdin based choosers on windows, so the terminal picker is used
+            if picker.query.is_some() || cfg!(windows) {
+                let selected_entry = select_entry(
+                    &mut expanded,
+                    &picker,
+                    &config,
+                    Some((&mut entries, &entries_filepath)),
+                )?;
+                if !picker.dry_run {
+                    record_open(&mut entries, selected_entry);
+                    save_opened(&entries_filepath, &entries)?;
+                }
+
+                open_entry(
+                    selected_entry,
+                    Backend::Terminal {
+                        new_window,
+                        new_session: false,
+                    },
+                    &config,
+                    verbosity,
+                    picker.dry_run,
+                )?;
+
+                return Ok(());
+            }
+
+            let labels = unique_labels(entry_labels(&expanded, &picker, &config, false));
+
+            let candidates = config.gui_choosers();
+            let chooser = candidates
+                .iter()
+                .copied()
+                .find(|chooser| is_in_path(chooser.program()))
+                .ok_or_else(|| {
+                    let tried: Vec<&str> = candidates.iter().map(|c| c.program()).collect();
+                    eyre!(
+                        "none of the gui choosers is installed (tried {})",
+                        tried.join(", ")
+                    )
+                })?;
+            verbosity.info(format!("using {} as chooser", chooser.program()));
+
+            let Some(idx) = chooser_select(chooser, &labels)? else {
+                return Err(Cancelled.into());
+            };
+            let selected_entry = expanded
+                .get(idx)
+                .ok_or(eyre!("the chooser returned an invalid index ({idx})"))?;
+
+            if !picker.dry_run {
+                record_open(&mut entries, selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
+
+            open_entry(
+                selected_entry,
+                Backend::Terminal {
+                    new_window,
+                    new_session: false,
+                },
+                &config,
+                verbosity,
+                picker.dry_run,
+            )?;
+
+            Ok(())
+        }
+        Action::List {
+            format,
+            expand,
+            sort,
+            all,
+        } => {
+            let mut listed = if expand {
+                expand_entries(&entries, None, all, warn_above, &mut cache, verbosity)?
             } else {
-                entries.push_back(Entry(path));
+                Vec::from(entries)
+            };
+            if !all {
+                listed.retain(|entry| !entry.archived);
+            }
+            if let Some(key) = sort {
+                sort_entries(&mut listed, key);
             }
 
-            serde_json::to_writer_pretty(File::create(&entries_filepath)?, &entries)?;
+            match format {
+                ListFormat::Plain => {
+                    let ansi = should_use_color(&std::io::stdout());
+                    for entry in &listed {
+                        if ansi {
+                            println!("{}", entry.decorated_label(&config));
+                        } else {
+                            println!("{}", entry.label());
+                        }
+                    }
+                }
+                ListFormat::Json => println!("{}", serde_json::to_string_pretty(&listed)?),
+                ListFormat::Paths => listed
+                    .iter()
+                    .for_each(|entry| println!("{}", entry.path.display())),
+            }
 
             Ok(())
         }
-        Cli::Remove { path } => {
-            if let Some(path) = path {
-                entries.retain(|entry| *entry.0 != path);
+        Action::Completions { .. } | Action::Init { .. } | Action::Edit | Action::Undo => {
+            unreachable!("handled before loading the entries")
+        }
+        Action::Search { query, expand } => {
+            let mut candidates = if expand {
+                expand_entries(&entries, None, false, warn_above, &mut cache, verbosity)?
             } else {
-                let mut selected_entries = MultiSelect::with_theme(&ColorfulTheme::default())
-                    .items(entries.make_contiguous())
-                    .interact_opt()?
-                    .unwrap_or_else(|| std::process::exit(1));
-                selected_entries.sort();
+                Vec::from(entries)
+            };
+            candidates.retain(|entry| !entry.archived);
 
-                selected_entries.iter().rev().for_each(|idx| {
-                    entries.remove(*idx);
-                });
+            let query = query.to_lowercase();
+            let mut found = false;
+            for entry in candidates {
+                if entry.path.to_string_lossy().to_lowercase().contains(&query) {
+                    println!("{}", entry.path.display());
+                    found = true;
+                }
             }
 
-            serde_json::to_writer_pretty(File::create(&entries_filepath)?, &entries)?;
+            if !found {
+                return Err(Failure(format!("no entries contain `{query}`")).into());
+            }
 
             Ok(())
         }
-    }
-}
+        Action::Stats => {
+            let patterns = entries.iter().filter(|entry| entry.is_pattern()).count();
+            let archived = entries.iter().filter(|entry| entry.archived).count();
 
-fn wezterm_open_path_in_tab(path: &Path, new_window: bool) -> color_eyre::Result<()> {
-    let mut command = Command::new("wezterm");
-    command
-        .current_dir(path)
-        .args(["cli", "spawn", "--cwd"])
-        .arg(path);
+            // a single pass including the archived entries, counted per entry it came from
+            let (expanded, warnings) = expand(&entries, None, true, None, warn_above);
+            print_warnings(&warnings, verbosity);
+            let mut matches = vec![0; entries.len()];
+            for source in expanded.iter().filter_map(|entry| entry.source) {
+                matches[source] += 1;
+            }
 
-    if new_window {
-        command.arg("--new-window");
-    }
-    command.arg("tmux");
+            let existing = matches.iter().filter(|count| **count > 0).count();
+            let empty_patterns = entries
+                .iter()
+                .zip(&matches)
+                .filter(|(entry, count)| entry.is_pattern() && **count == 0)
+                .count();
+            let unarchived: usize = entries
+                .iter()
+                .zip(&matches)
+                .filter(|(entry, _)| !entry.archived)
+                .map(|(_, count)| count)
+                .sum();
 
-    if let Some(name) = path.file_name() {
-        if tmux_session_exists(&name.to_string_lossy())? {
-            command.args(["a", "-t"]);
-            command.arg(name);
-        } else {
-            command.args(["new", "-s"]);
-            command.arg(name);
+            println!("entries:           {}", entries.len());
+            println!("  patterns:        {patterns}");
+            println!("  paths:           {}", entries.len() - patterns);
+            println!("  existing:        {existing}");
+            println!("  empty patterns:  {empty_patterns}");
+            println!("  archived:        {archived}");
+            println!("expanded entries:  {unarchived}");
+
+            if let Some(most_opened) = entries
+                .iter()
+                .filter(|entry| entry.open_count > 0)
+                .max_by_key(|entry| entry.open_count)
+            {
+                println!(
+                    "most opened:       {most_opened} ({} times)",
+                    most_opened.open_count
+                );
+            }
+
+            Ok(())
         }
-    }
+        Action::Recent { limit } => {
+            let mut entries = Vec::from(entries);
+            entries.retain(|entry| !entry.archived);
+            // `None` sorts before `Some`, so reversed never opened entries end up last
+            entries.sort_by_key(|entry| Reverse(entry.last_opened));
 
-    let status = command.spawn()?.wait()?;
-    if !status.success() {
-        eprintln!("failed to spawn tab: {status}");
-    };
+            let now = unix_now();
+            for entry in entries.iter().take(limit.unwrap_or(10)) {
+                match entry.last_opened {
+                    Some(last_opened) => println!(
+                        "{:>10}  {}",
+                        format_elapsed(now.saturating_sub(last_opened)),
+                        entry.path.display()
+                    ),
+                    None => println!("{:>10}  {}", "never", entry.path.display()),
+                }
+            }
 
-    Ok(())
-}
+            Ok(())
+        }
+        Action::Last { n, dry_run } => {
+            let mut opened: Vec<(usize, &Entry)> = entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.last_opened.is_some() && !entry.archived)
+                .collect();
+            if opened.is_empty() {
+                return Err(Failure("no project has been opened yet".to_string()).into());
+            }
+            opened.sort_by_key(|(_, entry)| Reverse(entry.last_opened));
 
-fn open_tmux_session(path: &Path) -> color_eyre::Result<()> {
-    let mut command = Command::new("tmux");
+            let Some((idx, stored)) = opened.get(n as usize - 1) else {
+                return Err(Failure(format!(
+                    "only {} projects have been opened so far",
+                    opened.len()
+                ))
+                .into());
+            };
 
-    if let Some(name) = path.file_name() {
-        if tmux_session_exists(&name.to_string_lossy())? {
-            command.args(["a", "-t"]);
-            command.arg(name);
-        } else {
-            command.args(["new", "-s"]);
-            command.arg(name);
-            command.arg("-c");
-            command.arg(path);
+            let mut selected_entry = (*stored).clone();
+            if stored.is_pattern() {
+                let Some(path) = &stored.last_opened_path else {
+                    return Err(eyre!("it's unknown which path of {stored} was opened last"));
+                };
+                selected_entry = selected_entry.with_path(path.clone());
+            } else if stored.remote().is_none() {
+                selected_entry = selected_entry.with_path(stored.expanded_path()?);
+            }
+            selected_entry.source = Some(*idx);
+
+            let backend = open_backend(selected_entry.backend, false, false, false, &config)?;
+            if !dry_run {
+                record_open(&mut entries, &selected_entry);
+                save_opened(&entries_filepath, &entries)?;
+            }
+
+            open_entry(&selected_entry, backend, &config, verbosity, dry_run)?;
+
+            Ok(())
         }
-    }
+        Action::Add {
+            paths,
+            name,
+            description,
+            tags,
+            exclude,
+            max_depth,
+            allow_files,
+            run,
+            cwd,
+            windows,
+            files,
+            backend,
+            prepend,
+        } => {
+            let paths = if paths.is_empty() {
+                vec![browse_directory()?]
+            } else {
+                paths
+                    .iter()
+                    .map(|path| resolve_new_path(path))
+                    .collect::<color_eyre::Result<_>>()?
+            };
 
-    let status = command.spawn()?.wait()?;
-    if !status.success() {
-        eprintln!("failed to open tmux session: {status}");
-    };
+            if name.is_some() && paths.len() > 1 {
+                return Err(eyre!("a name can only be given when adding a single path"));
+            }
 
-    Ok(())
-}
+            for pattern in &exclude {
+                glob::Pattern::new(pattern)
+                    .wrap_err_with(|| format!("invalid exclude pattern `{pattern}`"))?;
+            }
+
+            let cwd = cwd.as_deref().map(expand_path).transpose()?;
+
+            if let Some(file) = files.iter().find(|file| file.is_absolute()) {
+                return Err(eyre!(
+                    "bookmarked files have to be relative to the path, {} isn't",
+                    file.display()
+                ));
+            }
+
+            let mut known: HashSet<PathBuf> = entries
+                .iter()
+                .map(|entry| normalized_path(&entry.path))
+                .collect();
+            let mut added = Vec::with_capacity(paths.len());
+            let mut skipped = 0;
 
-fn tmux_session_exists(session_name: &str) -> color_eyre::Result<bool> {
-    Ok(String::from_utf8(
-        Command::new("tmux")
-            .args(["ls", "-F", "#{session_name}"])
-            .output()?
-            .stdout,
-    ).wrap_err("expected tmux ls to output valid utf-8")?
-    .lines()
-    .any(|existing| session_name == existing.trim()))
+            for path in paths {
+                if !known.insert(normalized_path(&path)) {
+                    eprintln!("{} is already in the list", path.display());
+                    skipped += 1;
+                    continue;
+                }
+
+                added.push(Entry {
+                    name: name.clone(),
+                    description: description.clone(),
+                    tags: tags.clone(),
+                    exclude: exclude.clone(),
+                    max_depth,
+                    allow_files,
+                    run: run.clone(),
+                    cwd: cwd.clone(),
+                    windows: windows.clone(),
+                    files: files.clone(),
+                    backend,
+                    ..Entry::new(path)
+                });
+            }
+
+            println!(
+                "added {} entries, skipped {skipped} duplicates",
+                added.len()
+            );
+            if added.is_empty() {
+                return Ok(());
+            }
+
+            if prepend {
+                for entry in added.into_iter().rev() {
+                    entries.push_front(entry);
+                }
+            } else {
+                entries.extend(added);
+            }
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Clone { url, into, open } => {
+            let target = match into {
+                Some(into) => expand_path(&into)?,
+                None => {
+                    let Some(root) = &config.projects_root else {
+                        return Err(eyre!(
+                            "either give a directory to clone into or set `projects_root` in the config"
+                        ));
+                    };
+                    let name = git::repo_name(&url)
+                        .ok_or_else(|| eyre!("unable to tell the repository name of {url}"))?;
+                    expand_path(Path::new(root))?.join(name)
+                }
+            };
+            if target.try_exists()? {
+                return Err(eyre!("{} already exists", target.display()));
+            }
+
+            let cloned = run_command(
+                Command::new("git").arg("clone").arg(&url).arg(&target),
+                "git clone failed",
+                verbosity,
+                false,
+            )?;
+            if !cloned {
+                return Err(eyre!("unable to clone {url}"));
+            }
+
+            let path = canonicalize(&target)?;
+            let normalized = normalized_path(&path);
+            let idx = match entries
+                .iter()
+                .position(|entry| normalized_path(&entry.path) == normalized)
+            {
+                Some(idx) => idx,
+                None => {
+                    println!("added {}", path.display());
+                    entries.push_back(Entry::new(path));
+                    entries.len() - 1
+                }
+            };
+
+            let opened = open.then(|| {
+                let mut entry = entries[idx].clone();
+                entry.source = Some(idx);
+                record_open(&mut entries, &entry);
+                entry
+            });
+
+            save_entries(&entries_filepath, &entries)?;
+
+            if let Some(entry) = opened {
+                let backend = open_backend(entry.backend, false, false, false, &config)?;
+                open_entry(&entry, backend, &config, verbosity, false)?;
+            }
+
+            Ok(())
+        }
+        Action::Here { prepend } => {
+            let path = canonicalize(&std::env::current_dir()?)?;
+
+            let normalized = normalized_path(&path);
+            if entries
+                .iter()
+                .any(|entry| normalized_path(&entry.path) == normalized)
+            {
+                eprintln!("{} is already in the list", path.display());
+                return Ok(());
+            }
+
+            println!("added {}", path.display());
+            if prepend {
+                entries.push_front(Entry::new(path));
+            } else {
+                entries.push_back(Entry::new(path));
+            }
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Remove { path, yes } => {
+            let to_remove: Vec<usize> = if let Some(path) = path {
+                let path = expand_path(&path)?;
+
+                let to_remove: Vec<usize> = if is_glob_pattern(&path) {
+                    let path = std::env::current_dir()?.join(&path);
+                    let pattern = glob::Pattern::new(&path.to_string_lossy())?;
+                    // stored patterns are removed if they are given literally
+                    (0..entries.len())
+                        .filter(|idx| {
+                            entries[*idx].path == path || pattern.matches_path(&entries[*idx].path)
+                        })
+                        .collect()
+                } else {
+                    let normalized = normalized_path(&path);
+                    (0..entries.len())
+                        .filter(|idx| normalized_path(&entries[*idx].path) == normalized)
+                        .collect()
+                };
+
+                if to_remove.is_empty() {
+                    return Err(Failure(format!("no entries matched {}", path.display())).into());
+                }
+                to_remove
+            } else {
+                // indices of the checklist have to be the indices into the deque
+                let labels: Vec<String> = entries.iter().map(Entry::label).collect();
+                MultiSelect::with_theme(&*theme())
+                    .items(&labels)
+                    .interact_opt()?
+                    .ok_or(Cancelled)?
+            };
+
+            if to_remove.is_empty() {
+                println!("nothing selected");
+                return Ok(());
+            }
+
+            if !yes {
+                if !std::io::stdin().is_terminal() {
+                    return Err(eyre!("pass --yes to remove entries without a terminal"));
+                }
+
+                for idx in &to_remove {
+                    println!("{}", entries[*idx]);
+                }
+                let confirmed = Confirm::with_theme(&*theme())
+                    .with_prompt(format!("remove {} entries?", to_remove.len()))
+                    .default(false)
+                    .interact_opt()?
+                    .unwrap_or(false);
+                if !confirmed {
+                    return Err(Cancelled.into());
+                }
+            }
+
+            let removed = remove_entries(&mut entries, &to_remove);
+            println!("removed {} entries", removed.len());
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Move { path, to } => {
+            let (from, to) = match (path, to) {
+                (Some(path), Some(to)) => {
+                    let from = find_entry(&entries, &path)?;
+
+                    (from, to)
+                }
+                _ => {
+                    let from = Select::with_theme(&*theme())
+                        .with_prompt("entry to move")
+                        .items(entries.make_contiguous())
+                        .interact_opt()?
+                        .ok_or(Cancelled)?;
+
+                    let positions: Vec<String> = entries
+                        .iter()
+                        .enumerate()
+                        .filter(|(idx, _)| *idx != from)
+                        .map(|(_, entry)| format!("before {entry}"))
+                        .chain(["at the end".to_string()])
+                        .collect();
+                    let to = Select::with_theme(&*theme())
+                        .with_prompt("new position")
+                        .items(&positions)
+                        .interact_opt()?
+                        .ok_or(Cancelled)?;
+
+                    (from, to)
+                }
+            };
+
+            let entry = entries.remove(from).expect("index should be in bounds");
+            entries.insert(to.min(entries.len()), entry);
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Rename { old, new } => {
+            let idx = find_entry(&entries, &old)?;
+            entries[idx].path = resolve_new_path(&new)?;
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Pin { ref path } | Action::Unpin { ref path } => {
+            let pin = matches!(action, Action::Pin { .. });
+            let idx = find_entry(&entries, path)?;
+            entries[idx].pinned = pin;
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Archive { ref path } | Action::Unarchive { ref path } => {
+            let archive = matches!(action, Action::Archive { .. });
+            let idx = find_entry(&entries, path)?;
+            entries[idx].archived = archive;
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Validate | Action::Doctor => {
+            unreachable!("handled before the entries are loaded")
+        }
+        Action::ClearCache => {
+            if clear_cache(&cache_path)? {
+                println!("removed {}", cache_path.display());
+            } else {
+                println!("there is no cache");
+            }
+
+            Ok(())
+        }
+        Action::Dedupe => {
+            let removed = dedupe_entries(&mut entries);
+            save_entries(&entries_filepath, &entries)?;
+
+            println!("removed {removed} duplicate entries");
+
+            Ok(())
+        }
+        Action::Export { path } => {
+            match path {
+                Some(path) => {
+                    serde_json::to_writer_pretty(File::create(path)?, &ProjectList::new(&entries))?
+                }
+                None => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&ProjectList::new(&entries))?
+                ),
+            }
+
+            Ok(())
+        }
+        Action::ImportZoxide { min_score } => {
+            let output = Command::new("zoxide")
+                .args(["query", "--list", "--score"])
+                .output()
+                .wrap_err("unable to run zoxide, is it installed?")?;
+            if !output.status.success() {
+                return Err(eyre!(
+                    "zoxide query failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+
+            let mut known: HashSet<PathBuf> = entries
+                .iter()
+                .map(|entry| normalized_path(&entry.path))
+                .collect();
+            let mut added = 0;
+
+            for line in String::from_utf8(output.stdout)?.lines() {
+                let Some((score, path)) = line.trim().split_once(' ') else {
+                    continue;
+                };
+                let Ok(score) = score.parse::<f64>() else {
+                    continue;
+                };
+                if min_score.is_some_and(|min_score| score < min_score) {
+                    continue;
+                }
+
+                let path = PathBuf::from(path.trim_start());
+                if known.insert(normalized_path(&path)) {
+                    entries.push_back(Entry::new(path));
+                    added += 1;
+                }
+            }
+
+            save_entries(&entries_filepath, &entries)?;
+            println!("added {added} entries");
+
+            Ok(())
+        }
+        Action::Import { path, merge } => {
+            let content = std::fs::read_to_string(&path)?;
+            let (imported, _) = migrate_entries(&content, DataFormat::of(&path))
+                .wrap_err_with(|| format!("{} is not a valid project list", path.display()))?;
+
+            if merge {
+                entries.extend(imported);
+                dedupe_entries(&mut entries);
+            } else {
+                entries = imported;
+            }
+
+            save_entries(&entries_filepath, &entries)?;
+
+            Ok(())
+        }
+        Action::Prune { dry_run } => {
+            let mut kept = VecDeque::with_capacity(entries.len());
+
+            for entry in entries {
+                if entry.is_pattern() {
+                    // an empty pattern might match again later, so it's only reported
+                    let (matches, warnings) =
+                        expand(&VecDeque::from([entry.clone()]), None, true, None, None);
+                    print_warnings(&warnings, verbosity);
+                    if matches.is_empty() {
+                        println!("pattern matches nothing: {}", entry.path.display());
+                    }
+                    kept.push_back(entry);
+                // entries using variables which aren't set might exist on other machines, as do
+                // remote ones
+                } else if entry.remote().is_some()
+                    || entry
+                        .expanded_path()
+                        .map_or(Ok(true), |path| path.try_exists())?
+                {
+                    kept.push_back(entry);
+                } else if dry_run {
+                    println!("would remove: {}", entry.path.display());
+                } else {
+                    println!("removed: {}", entry.path.display());
+                }
+            }
+
+            if !dry_run {
+                save_entries(&entries_filepath, &kept)?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// the `op` function for the shell, which only changes the directory if a project was picked
+///
+/// it calls the binary the way we were called, so it also works if it isn't in `$PATH`, a
+/// relative path is made absolute as it wouldn't work after changing the directory
+fn init_script(shell: InitShell) -> String {
+    let mut bin = std::env::args()
+        .next()
+        .unwrap_or_else(|| "open-project".to_string());
+    if bin.contains(std::path::is_separator) {
+        if let Ok(exe) = std::env::current_exe() {
+            bin = exe.to_string_lossy().into_owned();
+        }
+    }
+
+    match shell {
+        InitShell::Bash | InitShell::Zsh => format!(
+            "op() {{\n    local dir\n    dir=\"$({} pick \"$@\")\" && cd -- \"$dir\"\n}}\n",
+            shell_quote(&bin)
+        ),
+        // fish escapes quotes inside single quotes with a backslash
+        InitShell::Fish => format!(
+            "function op\n    set -l dir ('{}' pick $argv)\n    and cd -- $dir\nend\n",
+            bin.replace('\\', r"\\").replace('\'', r"\'")
+        ),
+    }
+}
+
+/// `projects.toml` in the data directory if it exists or is the configured format, otherwise
+/// `projects.json`
+///
+/// a broken config is only reported once it's loaded for real, until then json is assumed
+fn default_data_file_path(data_dir: &Path, config_path: &Path) -> color_eyre::Result<PathBuf> {
+    let toml_path = data_dir.join(DataFormat::Toml.filename());
+    let configured = Config::load(config_path)
+        .map(|config| config.data_format)
+        .unwrap_or_default();
+
+    if configured == DataFormat::Toml || toml_path.try_exists()? {
+        Ok(toml_path)
+    } else {
+        Ok(data_dir.join(DataFormat::Json.filename()))
+    }
+}
+
+/// [`open`]s the entry, asking for what the library leaves to the caller first: which bookmarked
+/// file to open in the editor and whether to reuse a tmux session of another project
+fn open_entry(
+    entry: &Entry,
+    mut backend: Backend,
+    config: &Config,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let uses_tmux = match backend {
+        Backend::Tmux { .. } => true,
+        Backend::Session { .. } | Backend::Terminal { .. } => {
+            config.multiplexer == Multiplexer::Tmux
+        }
+        _ => false,
+    };
+
+    match &mut backend {
+        Backend::Editor { file, .. } if file.is_none() => *file = select_bookmarked_file(entry)?,
+        Backend::Session { new_session }
+        | Backend::Tmux { new_session }
+        | Backend::Terminal { new_session, .. }
+            if uses_tmux =>
+        {
+            *new_session = confirm_new_session(entry, *new_session)?;
+        }
+        _ => {}
+    }
+
+    open(entry, backend, config, verbosity, dry_run)
+}
+
+/// lets the user choose between the entry's path and its bookmarked files, `None` meaning the path
+///
+/// there's nothing to choose without bookmarks or a terminal to ask in
+fn select_bookmarked_file(entry: &Entry) -> color_eyre::Result<Option<PathBuf>> {
+    if entry.files.is_empty() || !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    let items: Vec<String> = std::iter::once("<open the project>".to_string())
+        .chain(entry.files.iter().map(|file| file.display().to_string()))
+        .collect();
+    let selection = Select::with_theme(&*theme())
+        .with_prompt(entry.to_string())
+        .items(&items)
+        .default(0)
+        .interact_opt()?
+        .ok_or(Cancelled)?;
+
+    Ok(selection
+        .checked_sub(1)
+        .map(|idx| entry.path.join(&entry.files[idx])))
+}
+
+/// whether to start a new tmux session for the entry, asking if the one it would attach to was
+/// started in another directory and likely belongs to another project with the same name
+fn confirm_new_session(entry: &Entry, new_session: bool) -> color_eyre::Result<bool> {
+    if new_session || !std::io::stdin().is_terminal() {
+        return Ok(new_session);
+    }
+    let Some(conflict) = tmux_session_conflict(entry)? else {
+        return Ok(false);
+    };
+
+    let choice = Select::with_theme(&*theme())
+        .with_prompt(format!(
+            "session `{}` belongs to {}",
+            conflict.name,
+            conflict.session_path.display()
+        ))
+        .items(&[
+            format!("attach to `{}` anyway", conflict.name),
+            format!("create a new session `{}`", conflict.new_name),
+        ])
+        .default(1)
+        .interact_opt()?
+        .ok_or(Cancelled)?;
+
+    Ok(choice == 1)
+}
+
+/// [`load_entries`], offering to start over with an empty list if the file can't be parsed
+fn load_or_reset_entries(path: &Path) -> color_eyre::Result<VecDeque<Entry>> {
+    let err = match load_entries(path) {
+        Ok(entries) => return Ok(entries),
+        Err(err) => err,
+    };
+    if !std::io::stdin().is_terminal() || !is_syntax_error(&err) {
+        return Err(err);
+    }
+
+    eprintln!("{err:#}");
+    let reset = Confirm::with_theme(&*theme())
+        .with_prompt(format!(
+            "move it to {} and start with an empty list?",
+            broken_list_path(path).display()
+        ))
+        .default(false)
+        .interact_opt()?
+        .unwrap_or(false);
+    if !reset {
+        return Err(Cancelled.into());
+    }
+
+    reset_entries(path)
+}
+
+/// the backend `open` uses, failing for flags which the backend can't do anything with
+fn open_backend(
+    kind: Option<BackendKind>,
+    new_window: bool,
+    new_session: bool,
+    detach: bool,
+    config: &Config,
+) -> color_eyre::Result<Backend> {
+    let name = kind.and_then(|kind| kind.to_possible_value()).map_or_else(
+        || "the default".to_string(),
+        |value| format!("the {}", value.get_name()),
+    );
+
+    let tmux = match kind {
+        None => config.multiplexer == Multiplexer::Tmux,
+        Some(kind) => kind == BackendKind::Tmux,
+    };
+    if detach && (!tmux || !cfg!(unix)) {
+        return Err(eyre!("--detach only works with tmux as the multiplexer"));
+    }
+
+    if new_window {
+        match kind {
+            Some(BackendKind::Terminal) => {}
+            Some(BackendKind::Editor) => {
+                let editor = preferred_editor(config);
+                if !is_gui_editor(&editor) {
+                    return Err(eyre!(
+                        "--new-window only works with gui editors, {editor} runs in the terminal"
+                    ));
+                }
+            }
+            _ => {
+                return Err(eyre!(
+                    "--new-window doesn't work with {name} backend, only with terminal and editor"
+                ))
+            }
+        }
+    }
+
+    if new_session
+        && !matches!(
+            kind,
+            None | Some(BackendKind::Tmux) | Some(BackendKind::Terminal)
+        )
+    {
+        return Err(eyre!("--new-session doesn't work with {name} backend"));
+    }
+
+    Ok(match kind {
+        None => Backend::Session { new_session },
+        Some(BackendKind::Tmux) => Backend::Tmux { new_session },
+        Some(BackendKind::Zellij) => Backend::Zellij,
+        Some(BackendKind::Terminal) => Backend::Terminal {
+            new_window,
+            new_session,
+        },
+        Some(BackendKind::Editor) => Backend::Editor {
+            new_window,
+            file: None,
+        },
+        Some(BackendKind::Files) => Backend::Files,
+    })
+}
+
+/// prints problems with the project list and config, returning whether there were no errors
+///
+/// missing programs are only warnings, since not every command needs all of them
+fn validate(entries_path: &Path, config_path: &Path) -> color_eyre::Result<bool> {
+    let mut errors = 0;
+    let mut warnings = 0;
+    let mut error = |message: String| {
+        errors += 1;
+        println!("error: {message}");
+    };
+
+    let config = match Config::load(config_path) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            error(format!("{err:#}"));
+            None
+        }
+    };
+
+    let content = if entries_path.try_exists()? {
+        std::fs::read_to_string(entries_path)?
+    } else {
+        error(format!(
+            "there is no project list at {}",
+            entries_path.display()
+        ));
+        String::new()
+    };
+    let entries = if content.trim().is_empty() {
+        VecDeque::new()
+    } else {
+        match migrate_entries(&content, DataFormat::of(entries_path)) {
+            Ok((entries, outdated)) => {
+                if outdated {
+                    println!(
+                        "note: the project list uses an older format, it's updated when it's \
+                         loaded next"
+                    );
+                }
+                entries
+            }
+            Err(err) => {
+                error(format!(
+                    "invalid project list at {}: {err:#}",
+                    entries_path.display()
+                ));
+                VecDeque::new()
+            }
+        }
+    };
+
+    for entry in &entries {
+        let path = entry.path.display();
+        match entry.expanded_path() {
+            Err(err) => error(format!("{err:#}")),
+            Ok(expanded) if entry.is_pattern() => {
+                if let Err(err) = glob::Pattern::new(&expanded.to_string_lossy()) {
+                    error(format!("invalid pattern {path}: {err}"));
+                }
+            }
+            Ok(expanded) if !expanded.is_dir() && entry.remote().is_none() => {
+                error(format!("{path} is not an existing directory"));
+            }
+            Ok(_) => {}
+        }
+
+        for pattern in &entry.exclude {
+            if let Err(err) = glob::Pattern::new(pattern) {
+                error(format!(
+                    "invalid exclude pattern `{pattern}` of {path}: {err}"
+                ));
+            }
+        }
+    }
+
+    let mut warning = |message: String| {
+        warnings += 1;
+        println!("warning: {message}");
+    };
+
+    let mut deduped = entries.clone();
+    let duplicates = dedupe_entries(&mut deduped);
+    if duplicates > 0 {
+        warning(format!(
+            "{duplicates} entries point to the same path as another one, run `dedupe` to merge them"
+        ));
+    }
+
+    if let Some(config) = config {
+        let terminal = config.terminal.program();
+        if !is_in_path(terminal) {
+            warning(format!(
+                "the configured terminal ({terminal}) isn't installed"
+            ));
+        }
+
+        if let Some(multiplexer) = config.multiplexer.program() {
+            if cfg!(unix) && !is_in_path(multiplexer) {
+                warning(format!(
+                    "the configured multiplexer ({multiplexer}) isn't installed"
+                ));
+            }
+        }
+
+        let choosers = config.gui_choosers();
+        if !cfg!(windows) && !choosers.iter().any(|chooser| is_in_path(chooser.program())) {
+            let tried: Vec<&str> = choosers.iter().map(|c| c.program()).collect();
+            warning(format!(
+                "none of the gui choosers is installed (tried {})",
+                tried.join(", ")
+            ));
+        }
+    }
+
+    println!("{errors} errors, {warnings} warnings");
+
+    Ok(errors == 0)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CheckResult {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// prints a check per program and setting relevant for opening projects, returning whether none
+/// of them failed
+fn doctor(entries_path: &Path, config_path: &Path) -> bool {
+    let mut results = Vec::new();
+    let mut report = |result: CheckResult, message: String| {
+        let label = match result {
+            CheckResult::Pass => "PASS",
+            CheckResult::Warn => "WARN",
+            CheckResult::Fail => "FAIL",
+        };
+        println!("{label}  {message}");
+        results.push(result);
+    };
+
+    match std::fs::read_to_string(entries_path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => report(
+            CheckResult::Fail,
+            format!("project list: {} (missing)", entries_path.display()),
+        ),
+        Err(err) => report(
+            CheckResult::Fail,
+            format!("project list: {}: {err}", entries_path.display()),
+        ),
+        Ok(content) if content.trim().is_empty() => report(
+            CheckResult::Pass,
+            format!("project list: {} (empty)", entries_path.display()),
+        ),
+        Ok(content) => match migrate_entries(&content, DataFormat::of(entries_path)) {
+            Ok((entries, _)) => report(
+                CheckResult::Pass,
+                format!(
+                    "project list: {} ({} entries)",
+                    entries_path.display(),
+                    entries.len()
+                ),
+            ),
+            Err(err) => report(
+                CheckResult::Fail,
+                format!(
+                    "invalid project list at {}: {err:#}",
+                    entries_path.display()
+                ),
+            ),
+        },
+    }
+
+    let config = if !config_path.exists() {
+        report(
+            CheckResult::Pass,
+            format!(
+                "config: {} (missing, using defaults)",
+                config_path.display()
+            ),
+        );
+        Config::default()
+    } else {
+        match Config::load(config_path) {
+            Ok(config) => {
+                report(
+                    CheckResult::Pass,
+                    format!("config: {}", config_path.display()),
+                );
+                config
+            }
+            Err(err) => {
+                report(CheckResult::Fail, format!("{err:#}"));
+                Config::default()
+            }
+        }
+    };
+
+    let multiplexer = config.multiplexer.program().filter(|_| cfg!(unix));
+    let terminal = config.terminal.program();
+    let choosers = config.gui_choosers();
+
+    let mut programs = vec!["tmux", "zellij", "wezterm", "kitty", "fzf", "git"];
+    programs.extend(choosers.iter().map(|chooser| chooser.program()));
+    for program in programs {
+        let configured = Some(program) == multiplexer || program == terminal;
+        match (is_in_path(program), program_version(program)) {
+            (true, Some(version)) => report(CheckResult::Pass, format!("{program}: {version}")),
+            (true, None) => report(CheckResult::Pass, format!("{program}: installed")),
+            (false, _) if configured => report(
+                CheckResult::Fail,
+                format!("{program}: not installed, but configured to be used"),
+            ),
+            (false, _) => report(CheckResult::Warn, format!("{program}: not installed")),
+        }
+    }
+
+    if !cfg!(windows) && !choosers.iter().any(|chooser| is_in_path(chooser.program())) {
+        report(
+            CheckResult::Warn,
+            "none of the gui choosers is installed, open-gui won't work".to_string(),
+        );
+    }
+
+    match std::env::var_os("TMUX") {
+        Some(_) => report(
+            CheckResult::Pass,
+            "$TMUX is set, sessions are switched to".to_string(),
+        ),
+        None => report(
+            CheckResult::Pass,
+            "$TMUX isn't set, sessions are attached to".to_string(),
+        ),
+    }
+
+    match std::env::var("EDITOR") {
+        Ok(editor) if !editor.trim().is_empty() => {
+            report(CheckResult::Pass, format!("$EDITOR is {editor}"))
+        }
+        _ => report(
+            CheckResult::Warn,
+            format!(
+                "$EDITOR isn't set, open-editor uses {}",
+                preferred_editor(&config)
+            ),
+        ),
+    }
+
+    let failed = results.contains(&CheckResult::Fail);
+    let count = |result| results.iter().filter(|r| **r == result).count();
+    println!(
+        "{} passed, {} warnings, {} failed",
+        count(CheckResult::Pass),
+        count(CheckResult::Warn),
+        count(CheckResult::Fail)
+    );
+
+    !failed
+}
+
+/// the first line the program prints about its version, if it has a known flag for it
+fn program_version(program: &str) -> Option<String> {
+    let flag = match program {
+        "tmux" => "-V",
+        "rofi" => "-version",
+        "choose" => return None,
+        _ => "--version",
+    };
+
+    let output = Command::new(program)
+        .arg(flag)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+}
+
+/// turns a path given by the user into the form it's stored as
+///
+/// patterns can't be canonicalized, but plain paths are stored absolute so they don't depend on
+/// the directory we're run from, unless they refer to environment variables, which are kept so
+/// the list can be shared between machines
+fn resolve_new_path(path: &Path) -> color_eyre::Result<PathBuf> {
+    // remote paths are stored as given, there's no way to check them
+    if path.to_string_lossy().starts_with("ssh://") {
+        return match parse_remote(path) {
+            Some(_) => Ok(path.to_path_buf()),
+            None => Err(eyre!(
+                "{} isn't a valid remote path, they look like ssh://host/path/to/project",
+                path.display()
+            )),
+        };
+    }
+
+    let uses_variables = path.to_string_lossy().contains('$');
+    let path = if uses_variables {
+        // the variables are only validated here, but are stored unexpanded
+        let expanded = expand_path(path)?;
+        if is_glob_pattern(&expanded) || expanded.try_exists()? {
+            return Ok(path.to_path_buf());
+        }
+        expanded
+    } else {
+        expand_path(path)?
+    };
+
+    if is_glob_pattern(&path) {
+        Ok(path)
+    } else if path.try_exists()? {
+        Ok(canonicalize(&path)?)
+    } else {
+        Err(eyre!(
+            "{} doesn't exist (use `*`, `?` or `[` for a pattern)",
+            path.display()
+        ))
+    }
+}
+
+/// entries for the paths piped in, relative ones are relative to the current directory
+fn read_stdin_entries() -> color_eyre::Result<VecDeque<Entry>> {
+    let current_dir = std::env::current_dir()?;
+
+    std::io::stdin()
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| {
+            let line = line.wrap_err("unable to read the paths from stdin")?;
+            let path = Path::new(line.trim());
+            // `~` and variables are expanded later, joining would hide them
+            let path = if path.is_relative() && !line.trim_start().starts_with(['~', '$']) {
+                current_dir.join(path)
+            } else {
+                path.to_path_buf()
+            };
+            Ok(Entry::new(path))
+        })
+        .collect()
+}
+
+/// lets the user walk the directory tree starting at the home directory until they select one
+fn browse_directory() -> color_eyre::Result<PathBuf> {
+    const SELECT: &str = "<select this directory>";
+    const PARENT: &str = "..";
+
+    let mut current = directories::BaseDirs::new()
+        .ok_or(eyre!("unable to find the home directory"))?
+        .home_dir()
+        .to_path_buf();
+
+    loop {
+        let mut subdirs: Vec<String> = std::fs::read_dir(&current)?
+            .filter_map(Result::ok)
+            .filter(|dir_entry| dir_entry.path().is_dir())
+            .map(|dir_entry| dir_entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        subdirs.sort();
+
+        let items: Vec<&str> = [SELECT, PARENT]
+            .into_iter()
+            .chain(subdirs.iter().map(String::as_str))
+            .collect();
+
+        let selection = FuzzySelect::with_theme(&*theme())
+            .with_prompt(current.display().to_string())
+            .items(&items)
+            .interact_opt()?
+            .ok_or(Cancelled)?;
+
+        match items[selection] {
+            SELECT => return Ok(current),
+            PARENT => {
+                current.pop();
+            }
+            subdir => current.push(subdir),
+        }
+    }
+}
+
+/// index of the stored entry pointing to `path`, the error lists entries with a similar name
+fn find_entry(entries: &VecDeque<Entry>, path: &Path) -> color_eyre::Result<usize> {
+    let path = expand_path(path)?;
+    let normalized = normalized_path(&path);
+
+    if let Some(idx) = entries
+        .iter()
+        .position(|entry| normalized_path(&entry.path) == normalized)
+    {
+        return Ok(idx);
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let near_matches: Vec<String> = entries
+        .iter()
+        .filter(|entry| {
+            !name.is_empty() && entry.path.to_string_lossy().to_lowercase().contains(&name)
+        })
+        .map(|entry| format!("\n  {entry}"))
+        .collect();
+
+    if near_matches.is_empty() {
+        Err(eyre!("{} is not in the list", path.display()))
+    } else {
+        Err(eyre!(
+            "{} is not in the list, did you mean one of these?{}",
+            path.display(),
+            near_matches.concat()
+        ))
+    }
+}
+
+/// stamps the stored entry `opened` was expanded from
+///
+/// this has to be saved before opening, as attaching to a session blocks until detaching and the
+/// list might be changed by other commands meanwhile
+fn record_open(entries: &mut VecDeque<Entry>, opened: &Entry) {
+    if let Some(source) = opened.source.and_then(|idx| entries.get_mut(idx)) {
+        source.mark_opened();
+        if source.is_pattern() {
+            source.last_opened_path = Some(opened.path.clone());
+        }
+    }
+}
+
+/// formats a duration given in seconds as a short human readable string like `3h ago`
+fn format_elapsed(secs: u64) -> String {
+    let (amount, unit) = match secs {
+        0..=59 => (secs, "s"),
+        60..=3599 => (secs / 60, "m"),
+        3600..=86_399 => (secs / 3600, "h"),
+        _ => (secs / 86_400, "d"),
+    };
+    format!("{amount}{unit} ago")
+}
+
+/// expands all entries and only keeps the ones matching the picker options, exiting if none are
+/// left
+fn picker_entries(
+    entries: &VecDeque<Entry>,
+    picker: &PickerArgs,
+    config: &Config,
+    warn_above: Option<usize>,
+    cache: &mut Option<GlobCache>,
+    verbosity: Verbosity,
+) -> color_eyre::Result<Vec<Entry>> {
+    let mut entries = expand_entries(
+        entries,
+        (!picker.no_frecency).then(|| config.frecency_half_life()),
+        picker.include_archived,
+        warn_above,
+        cache,
+        verbosity,
+    )?;
+
+    if let Some(tag) = &picker.tag {
+        entries.retain(|entry| entry.has_tag(tag));
+
+        if entries.is_empty() {
+            return Err(Failure(format!("no entries matched tag {tag}")).into());
+        }
+    }
+
+    if let Some(query) = &picker.query {
+        entries = filter_by_query(entries, query);
+
+        if entries.is_empty() {
+            return Err(Failure(format!("no entries matched `{query}`")).into());
+        }
+    }
+
+    if picker.group {
+        // stable, so the order within a group is kept
+        entries.sort_by_key(|entry| entry.source);
+    }
+
+    // stable as well, pinned entries keep their order among themselves, as do the others
+    entries.sort_by_key(|entry| !entry.pinned);
+
+    if let Some(limit) = picker.limit {
+        entries.truncate(limit);
+    }
+
+    Ok(entries)
+}
+
+/// stable, so entries comparing equal keep their order
+fn sort_entries(entries: &mut [Entry], key: SortKey) {
+    match key {
+        SortKey::Name => entries
+            .sort_by_cached_key(|entry| entry.session_name().unwrap_or_default().to_lowercase()),
+        SortKey::Path => {
+            entries.sort_by_cached_key(|entry| entry.path.to_string_lossy().to_lowercase())
+        }
+        SortKey::Recent => entries.sort_by_key(|entry| Reverse(entry.last_opened)),
+        SortKey::Frequency => entries.sort_by_key(|entry| Reverse(entry.open_count)),
+    }
+}
+
+/// [`expand`] which prints the warnings and saves the cache afterwards
+fn expand_entries(
+    entries: &VecDeque<Entry>,
+    frecency_half_life: Option<f64>,
+    include_archived: bool,
+    warn_above: Option<usize>,
+    cache: &mut Option<GlobCache>,
+    verbosity: Verbosity,
+) -> color_eyre::Result<Vec<Entry>> {
+    let (expanded, warnings) = expand(
+        entries,
+        frecency_half_life,
+        include_archived,
+        cache.as_mut(),
+        warn_above,
+    );
+    print_warnings(&warnings, verbosity);
+    if let Some(cache) = cache {
+        cache.save()?;
+    }
+
+    Ok(expanded)
+}
+
+/// prints the warnings of an [`expand`] unless `-q` is given
+fn print_warnings(warnings: &[String], verbosity: Verbosity) {
+    if verbosity > Verbosity::Quiet {
+        for warning in warnings {
+            eprintln!("warning: {warning}");
+        }
+    }
+}
+
+/// keeps the entries matching `query`, trying an exact path match first, then the name and
+/// lastly a case insensitive substring of the path
+fn filter_by_query(entries: Vec<Entry>, query: &str) -> Vec<Entry> {
+    let query_path = PathBuf::from(shellexpand::tilde(query).as_ref());
+    let exact: Vec<Entry> = entries
+        .iter()
+        .filter(|entry| entry.path == query_path)
+        .cloned()
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+
+    let by_name: Vec<Entry> = entries
+        .iter()
+        .filter(|entry| {
+            entry.name.as_deref() == Some(query)
+                || entry
+                    .path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy() == query)
+        })
+        .cloned()
+        .collect();
+    if !by_name.is_empty() {
+        return by_name;
+    }
+
+    let query = query.to_lowercase();
+    entries
+        .into_iter()
+        .filter(|entry| entry.path.to_string_lossy().to_lowercase().contains(&query))
+        .collect()
+}
+
+/// lets the user pick one of the entries, skipping the prompt if a query narrowed it down to one
+///
+/// with the `project_list` the entries were expanded from and its path, ctrl-x in the builtin
+/// picker removes the stored entry of the highlighted one from it
+fn select_entry<'a>(
+    entries: &'a mut [Entry],
+    picker: &PickerArgs,
+    config: &Config,
+    project_list: Option<(&mut VecDeque<Entry>, &Path)>,
+) -> color_eyre::Result<&'a Entry> {
+    if picker.query.is_some() && entries.len() == 1 {
+        return Ok(&entries[0]);
+    }
+
+    let use_fzf = match picker.picker {
+        PickerKind::Auto => is_in_path("fzf"),
+        PickerKind::Fzf => true,
+        PickerKind::Builtin => false,
+    };
+
+    let labels = entry_labels(
+        entries,
+        picker,
+        config,
+        should_use_color(&std::io::stderr()),
+    );
+    let default = last_selection(entries).unwrap_or(0);
+    let selection = if use_fzf {
+        fzf_select(entries, &labels, default)?
+    } else {
+        let items: Vec<picker::Item> = entries
+            .iter()
+            .zip(labels)
+            .map(|(entry, label)| picker::Item {
+                label,
+                name: entry
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                keywords: entry.tags.clone(),
+            })
+            .collect();
+
+        match project_list {
+            Some((stored, path)) => {
+                let mut remove = |idx| remove_source(entries, idx, stored, path);
+                picker::fuzzy_select(&items, default, Some(&mut remove))?
+            }
+            None => picker::fuzzy_select(&items, default, None)?,
+        }
+    };
+
+    Ok(&entries[selection.ok_or(Cancelled)?])
+}
+
+/// removes the stored entry `entries[idx]` was expanded from and saves the list, returning the
+/// indices of all entries expanded from it
+///
+/// the other entries are kept pointing to their stored entry, which might have moved up
+fn remove_source(
+    entries: &mut [Entry],
+    idx: usize,
+    stored: &mut VecDeque<Entry>,
+    path: &Path,
+) -> color_eyre::Result<Vec<usize>> {
+    let Some(source) = entries[idx].source else {
+        return Ok(Vec::new());
+    };
+
+    stored.remove(source);
+    save_entries(path, stored)?;
+
+    let mut removed = Vec::new();
+    for (idx, entry) in entries.iter_mut().enumerate() {
+        match entry.source {
+            Some(other) if other == source => {
+                entry.source = None;
+                removed.push(idx);
+            }
+            Some(other) if other > source => entry.source = Some(other - 1),
+            _ => {}
+        }
+    }
+
+    Ok(removed)
+}
+
+/// index of the entry that was opened last, the stats of patterns are shared between their
+/// paths, so for them it has to be the path that was opened last
+fn last_selection(entries: &[Entry]) -> Option<usize> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| {
+            entry.pattern.is_none() || entry.last_opened_path.as_ref() == Some(&entry.path)
+        })
+        .filter(|(_, entry)| entry.last_opened.is_some())
+        .max_by_key(|(_, entry)| entry.last_opened)
+        .map(|(idx, _)| idx)
+}
+
+/// lets the user pick an entry with fzf, previewing the contents of the directory, starting at
+/// the `default` one
+fn fzf_select(
+    entries: &[Entry],
+    labels: &[String],
+    default: usize,
+) -> color_eyre::Result<Option<usize>> {
+    let (status, output) = match run_fzf(entries, labels, default)? {
+        // `load:pos` needs fzf 0.36, older versions reject it before showing anything
+        (status, _) if default > 0 && status.code() == Some(2) => run_fzf(entries, labels, 0)?,
+        result => result,
+    };
+
+    match status.code() {
+        Some(0) => Ok(output
+            .split('\t')
+            .next()
+            .and_then(|idx| idx.trim().parse().ok())),
+        // nothing matched or the user backed out
+        Some(1 | 130) => Ok(None),
+        _ => Err(eyre!("fzf failed: {status}")),
+    }
+}
+
+/// runs fzf with the cursor on `default`, returning how it exited and what it printed
+fn run_fzf(
+    entries: &[Entry],
+    labels: &[String],
+    default: usize,
+) -> color_eyre::Result<(ExitStatus, String)> {
+    let mut command = Command::new("fzf");
+    command.args([
+        "--delimiter",
+        "\t",
+        "--with-nth",
+        "2",
+        "--ansi",
+        "--preview",
+        "ls -la {3..}",
+    ]);
+    if default > 0 {
+        command
+            .arg("--bind")
+            .arg(format!("load:pos({})", default + 1));
+    }
+    let mut fzf = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .wrap_err("unable to start fzf")?;
+
+    let mut fzf_stdin = fzf
+        .stdin
+        .take()
+        .expect("should be able to take stdin of fzf");
+    // the index is hidden and used to find the entry again, the raw path is for the preview
+    for (idx, (entry, label)) in entries.iter().zip(labels).enumerate() {
+        match writeln!(fzf_stdin, "{idx}\t{label}\t{}", entry.path.display()) {
+            Ok(()) => {}
+            // fzf exited early, its status tells why
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    drop(fzf_stdin);
+
+    let output = fzf.wait_with_output()?;
+    Ok((output.status, String::from_utf8(output.stdout)?))
+}
+
+/// how the entries are shown in the pickers, `ansi` is whether the picker can show colors
+fn entry_labels(
+    entries: &[Entry],
+    picker: &PickerArgs,
+    config: &Config,
+    ansi: bool,
+) -> Vec<String> {
+    let mut labels: Vec<String> = entries
+        .iter()
+        .map(|entry| match (ansi, config.decorations) {
+            (true, _) => entry.decorated_label(config),
+            (false, true) => format!("{} {}", entry.icon(), entry.label()),
+            (false, false) => entry.label(),
+        })
+        .collect();
+
+    if picker.group {
+        for (label, entry) in labels.iter_mut().zip(entries) {
+            if let Some(pattern) = &entry.pattern {
+                label.insert_str(0, &format!("[{}] ", display_path(pattern)));
+            }
+        }
+    }
+
+    if picker.show_git {
+        let paths: Vec<PathBuf> = entries.iter().map(|entry| entry.path.clone()).collect();
+        for (label, status) in labels.iter_mut().zip(git::repo_statuses(&paths)) {
+            if let Some(status) = status {
+                let dirty = if status.dirty { "*" } else { "" };
+                label.push_str(&format!(" ({}{dirty})", status.branch));
+            }
+        }
+    }
+
+    // there are no multiplexers on windows
+    if let Some(marker) = config.session_marker().filter(|_| cfg!(unix)) {
+        let sessions = running_sessions(config.multiplexer);
+        for (label, entry) in labels.iter_mut().zip(entries) {
+            let running = entry.remote().is_none()
+                && entry
+                    .session_name()
+                    .is_some_and(|name| sessions.contains(&sanitize_session_name(&name)));
+            if running {
+                label.push_str(&format!(" {marker}"));
+            }
+        }
+    }
+
+    labels
+}
+
+/// appends a counter to labels that would otherwise show up identically so that the chooser's
+/// output can be mapped back to a single entry
+fn unique_labels(labels: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+
+    labels
+        .into_iter()
+        .map(|label| {
+            let mut unique = label.clone();
+            let mut counter = 1;
+            while !seen.insert(unique.clone()) {
+                counter += 1;
+                unique = format!("{label} ({counter})");
+            }
+            unique
+        })
+        .collect()
+}
+
+/// lets the chooser pick one of the labels and returns its index, `None` if it was cancelled
+fn chooser_select(chooser: GuiChooser, labels: &[String]) -> color_eyre::Result<Option<usize>> {
+    let mut child = chooser_command(chooser)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .wrap_err_with(|| format!("unable to start {}", chooser.program()))?;
+
+    let mut chooser_stdin = child
+        .stdin
+        .take()
+        .expect("should be able to take stdin of the chooser");
+    // choosers which can't print the selected row get the index prepended to every line instead
+    for (idx, label) in labels.iter().enumerate() {
+        if chooser.returns_index() {
+            writeln!(chooser_stdin, "{label}")?;
+        } else {
+            writeln!(chooser_stdin, "{idx}: {label}")?;
+        }
+    }
+    drop(chooser_stdin);
+
+    let output = String::from_utf8(child.wait_with_output()?.stdout)?;
+    let output = output.trim();
+    if output.is_empty() {
+        return Ok(None);
+    }
+
+    let idx = if chooser.returns_index() {
+        output
+    } else {
+        output.split_once(':').map_or(output, |(idx, _)| idx)
+    };
+
+    idx.trim()
+        .parse()
+        .map(Some)
+        .wrap_err_with(|| format!("unexpected output of {}: `{output}`", chooser.program()))
+}
+
+fn chooser_command(chooser: GuiChooser) -> Command {
+    match chooser {
+        GuiChooser::Anyrun => {
+            let mut anyrun = Command::new("anyrun");
+            anyrun.args([
+                "--plugins",
+                "libstdin.so",
+                "--show-results-immediately",
+                "true",
+            ]);
+            anyrun
+        }
+        GuiChooser::Rofi => {
+            let mut rofi = Command::new("rofi");
+            rofi.args(["-dmenu", "-i", "-format", "i"]);
+            rofi
+        }
+        GuiChooser::Wofi => {
+            let mut wofi = Command::new("wofi");
+            wofi.args(["--dmenu", "--insensitive"]);
+            wofi
+        }
+        GuiChooser::Fuzzel => {
+            let mut fuzzel = Command::new("fuzzel");
+            fuzzel.args(["--dmenu", "--index"]);
+            fuzzel
+        }
+        GuiChooser::Choose => {
+            let mut choose = Command::new("choose");
+            choose.arg("-i");
+            choose
+        }
+    }
 }
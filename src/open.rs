@@ -0,0 +1,1082 @@
+//! opening entries in the multiplexers, terminals, editors and file managers
+
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{eyre, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, Multiplexer, Terminal},
+    entry::{normalized_path, parse_remote, Entry},
+    git, is_in_path,
+    zellij::{zellij_session_status, zellij_sessions, Status},
+};
+
+/// where an entry is opened
+#[derive(Clone, Debug, PartialEq)]
+pub enum Backend {
+    /// the configured multiplexer in the current terminal, with `new_session` a new session is
+    /// started even if there already is one for the entry
+    Session { new_session: bool },
+    /// a tmux session in the current terminal, regardless of the configured multiplexer
+    Tmux { new_session: bool },
+    /// the configured multiplexer in a new tab (or window) of the configured terminal
+    Terminal { new_window: bool, new_session: bool },
+    /// the [preferred editor](preferred_editor) opening `file` instead of the path if given,
+    /// `new_window` only applies to gui editors
+    Editor {
+        new_window: bool,
+        file: Option<PathBuf>,
+    },
+    /// a zellij session, regardless of the configured multiplexer
+    Zellij,
+    /// the platform's file manager
+    Files,
+}
+
+/// the backends which can be chosen on the command line or stored for an entry
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// a tmux session in the current terminal
+    Tmux,
+    /// a zellij session in the current terminal
+    Zellij,
+    /// the configured multiplexer in a new tab of the configured terminal, like `open-term`
+    Terminal,
+    /// `$EDITOR`, like `open-editor`
+    Editor,
+    /// the file manager, like `open-files`
+    Files,
+}
+
+/// opens the entry with the backend, with `dry_run` the commands are only printed
+pub fn open(
+    entry: &Entry,
+    backend: Backend,
+    config: &Config,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    if entry.remote().is_some()
+        && !matches!(backend, Backend::Session { .. } | Backend::Tmux { .. })
+    {
+        return Err(eyre!(
+            "{entry} is on another host, it can only be opened in a tmux session there"
+        ));
+    }
+    let entry = &*with_git_root_cwd(entry, config);
+
+    match backend {
+        Backend::Session { new_session } => {
+            open_session(entry, new_session, config, verbosity, dry_run)
+        }
+        Backend::Tmux { new_session } => {
+            open_tmux_session(entry, None, new_session, verbosity, dry_run)
+        }
+        Backend::Terminal {
+            new_window,
+            new_session,
+        } => open_in_terminal(entry, new_window, new_session, config, verbosity, dry_run),
+        Backend::Editor { new_window, file } => {
+            open_in_editor(entry, file, new_window, config, verbosity, dry_run)
+        }
+        Backend::Zellij => open_zellij_session(entry, verbosity, dry_run),
+        Backend::Files => open_in_file_manager(entry, verbosity, dry_run),
+    }
+}
+
+/// with `cwd_from_git_root` configured, the entry starting in the repository its path is in,
+/// unless it has a `cwd` of its own
+fn with_git_root_cwd<'a>(entry: &'a Entry, config: &Config) -> Cow<'a, Entry> {
+    if !config.cwd_from_git_root || entry.cwd.is_some() || entry.remote().is_some() {
+        return Cow::Borrowed(entry);
+    }
+
+    match git::git_root(&entry.path) {
+        // absolute, so joining it onto the path replaces the path
+        Some(root) => Cow::Owned(Entry {
+            cwd: Some(root),
+            ..entry.clone()
+        }),
+        None => Cow::Borrowed(entry),
+    }
+}
+
+/// how much is reported about the commands being run
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn from_flags(verbose: u8, quiet: bool) -> Verbosity {
+        if quiet {
+            Verbosity::Quiet
+        } else if verbose > 0 {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+
+    /// prints the message at `-v`
+    pub fn info(self, message: impl Display) {
+        if self >= Verbosity::Verbose {
+            eprintln!("{message}");
+        }
+    }
+}
+
+/// runs the command to completion, printing it at `-v` and reporting if it failed unless `-q` is
+/// given, with `dry_run` it's only printed to stdout
+///
+/// returns whether the command succeeded
+pub fn run_command(
+    command: &mut Command,
+    failure: &str,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<bool> {
+    if dry_run {
+        println!("{command:?}");
+        return Ok(true);
+    }
+
+    verbosity.info(format!("running {command:?}"));
+
+    let status = command
+        .spawn()
+        .wrap_err_with(|| format!("unable to run {:?}", command.get_program()))?
+        .wait()?;
+    if !status.success() && verbosity > Verbosity::Quiet {
+        eprintln!("{failure}: {status}");
+    }
+
+    Ok(status.success())
+}
+
+/// opens the entry with the configured multiplexer in the current terminal
+///
+/// there are no multiplexers on windows, so a new tab of the terminal is opened there instead
+///
+/// remote entries are always opened in tmux on their host
+pub fn open_session(
+    entry: &Entry,
+    new_session: bool,
+    config: &Config,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    if entry.remote().is_some() {
+        return open_tmux_session(entry, None, new_session, verbosity, dry_run);
+    }
+
+    if !cfg!(unix) {
+        return open_in_terminal(entry, false, new_session, config, verbosity, dry_run);
+    }
+
+    match config.multiplexer {
+        Multiplexer::Tmux => open_tmux_session(entry, None, new_session, verbosity, dry_run),
+        Multiplexer::Zellij => open_zellij_session(entry, verbosity, dry_run),
+        Multiplexer::None => {
+            let shell = std::env::var_os("SHELL").unwrap_or_else(|| "sh".into());
+            let mut command = Command::new(shell);
+            command.current_dir(entry.working_dir());
+            run_command(&mut command, "shell failed", verbosity, dry_run)?;
+
+            Ok(())
+        }
+    }
+}
+
+/// opens the entry with the configured multiplexer in a new tab of the configured terminal
+pub fn open_in_terminal(
+    entry: &Entry,
+    new_window: bool,
+    new_session: bool,
+    config: &Config,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    match config.terminal {
+        Terminal::Wezterm => wezterm_open_path_in_tab(
+            entry,
+            new_window,
+            new_session,
+            config.multiplexer,
+            verbosity,
+            dry_run,
+        ),
+        Terminal::Kitty => kitty_open_path_in_tab(
+            entry,
+            new_window,
+            new_session,
+            config.multiplexer,
+            verbosity,
+            dry_run,
+        ),
+        Terminal::Wt => windows_terminal_open_path_in_tab(entry, new_window, verbosity, dry_run),
+    }
+}
+
+/// opens a tab (or window) of windows terminal in the entry's directory, falling back to the
+/// explorer if windows terminal isn't installed
+fn windows_terminal_open_path_in_tab(
+    entry: &Entry,
+    new_window: bool,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let mut command = Command::new("wt.exe");
+    command.args(["-w", if new_window { "new" } else { "0" }]);
+    command.args(["new-tab", "-d"]);
+    command.arg(entry.working_dir());
+
+    if !is_in_path("wt.exe") {
+        verbosity.info("windows terminal isn't installed, opening the explorer instead");
+        let mut explorer = Command::new("explorer");
+        explorer.arg(&entry.path);
+        if dry_run {
+            println!("{explorer:?}");
+        } else {
+            explorer.spawn()?;
+        }
+        return Ok(());
+    }
+
+    run_command(
+        &mut command,
+        "failed to open windows terminal",
+        verbosity,
+        dry_run,
+    )?;
+
+    Ok(())
+}
+
+/// how long to wait before retrying `wezterm cli`, there is one attempt more than delays
+const WEZTERM_RETRY_DELAYS: [Duration; 2] =
+    [Duration::from_millis(200), Duration::from_millis(500)];
+
+/// parts of `wezterm cli` errors meaning the mux server can't be reached (yet)
+const WEZTERM_UNREACHABLE: &[&str] = &[
+    "failed to connect",
+    "connection refused",
+    "no such domain",
+    "no such file or directory",
+];
+
+/// opens a tab (or window) in the running wezterm instance, falling back to starting a new
+/// wezterm window if there is none `wezterm cli` can reach, e.g. when run from a hotkey daemon
+///
+/// a wezterm that was just started might not accept connections yet, so unreachable ones are
+/// retried a few times first
+fn wezterm_open_path_in_tab(
+    entry: &Entry,
+    new_window: bool,
+    new_session: bool,
+    multiplexer: Multiplexer,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let path = &entry.working_dir();
+
+    // collected separately so the session isn't looked up (or asked about) twice
+    let mut program = Command::new("wezterm");
+    push_multiplexer_args(
+        &mut program,
+        entry,
+        multiplexer,
+        new_session,
+        verbosity,
+        dry_run,
+    )?;
+    let program: Vec<&OsStr> = program.get_args().collect();
+
+    let mut command = Command::new("wezterm");
+    command
+        .current_dir(path)
+        .args(["cli", "spawn", "--cwd"])
+        .arg(path);
+
+    if new_window {
+        command.arg("--new-window");
+    }
+
+    command.args(&program);
+
+    if dry_run {
+        println!("{command:?}");
+        return Ok(());
+    }
+
+    command.stdin(Stdio::null());
+    let mut delays = WEZTERM_RETRY_DELAYS.iter();
+    let error = loop {
+        verbosity.info(format!("running {command:?}"));
+        let output = command
+            .output()
+            .wrap_err("unable to run wezterm, is it installed?")?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let lowercase = error.to_lowercase();
+        if !WEZTERM_UNREACHABLE
+            .iter()
+            .any(|marker| lowercase.contains(marker))
+        {
+            return Err(eyre!("wezterm cli failed: {error}"));
+        }
+
+        match delays.next() {
+            Some(delay) => {
+                verbosity.info(format!("wezterm isn't reachable yet ({error}), retrying"));
+                std::thread::sleep(*delay);
+            }
+            None => break error,
+        }
+    };
+
+    verbosity.info(format!(
+        "wezterm cli failed ({error}), starting a new wezterm window instead"
+    ));
+
+    let mut start = Command::new("wezterm");
+    start
+        .current_dir(path)
+        .args(["start", "--cwd"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if !program.is_empty() {
+        start.arg("--").args(&program);
+    }
+
+    verbosity.info(format!("running {start:?}"));
+    start.spawn().wrap_err("unable to start wezterm")?;
+
+    Ok(())
+}
+
+fn kitty_open_path_in_tab(
+    entry: &Entry,
+    new_window: bool,
+    new_session: bool,
+    multiplexer: Multiplexer,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let path = &entry.working_dir();
+    let mut command = Command::new("kitty");
+    command
+        .current_dir(path)
+        .args(["@", "launch"])
+        .arg(if new_window {
+            "--type=os-window"
+        } else {
+            "--type=tab"
+        })
+        .arg("--cwd")
+        .arg(path);
+
+    push_multiplexer_args(
+        &mut command,
+        entry,
+        multiplexer,
+        new_session,
+        verbosity,
+        dry_run,
+    )?;
+
+    run_command(&mut command, "failed to spawn tab", verbosity, dry_run)?;
+
+    Ok(())
+}
+
+/// appends the command starting the multiplexer for the entry inside a new terminal tab
+///
+/// tmux sessions with multiple windows are created up front, so the tab only attaches to them
+fn push_multiplexer_args(
+    command: &mut Command,
+    entry: &Entry,
+    multiplexer: Multiplexer,
+    new_session: bool,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    match multiplexer {
+        Multiplexer::Tmux => {
+            command.arg("tmux");
+
+            if let Some((name, exists)) = tmux_target_session(entry, new_session, verbosity)? {
+                if !exists
+                    && !entry.windows.is_empty()
+                    && !new_detached_tmux_session(
+                        &name,
+                        entry,
+                        entry.run.as_deref(),
+                        verbosity,
+                        dry_run,
+                    )?
+                {
+                    return Err(eyre!("unable to create the tmux session {name}"));
+                }
+
+                if exists || !entry.windows.is_empty() {
+                    verbosity.info(format!("attaching to tmux session {name}"));
+                    command.args(["a", "-t"]);
+                    command.arg(&name);
+                } else {
+                    verbosity.info(format!("creating tmux session {name}"));
+                    command.args(["new", "-s"]);
+                    command.arg(&name);
+                    command.args(&entry.run);
+                }
+            }
+        }
+        Multiplexer::Zellij => {
+            command.arg("zellij");
+            push_zellij_args(command, entry, verbosity)?;
+        }
+        // the terminal starts the default shell if it doesn't get a command
+        Multiplexer::None => {}
+    }
+
+    Ok(())
+}
+
+pub fn open_zellij_session(
+    entry: &Entry,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let mut command = Command::new("zellij");
+    command.current_dir(entry.working_dir());
+    push_zellij_args(&mut command, entry, verbosity)?;
+
+    run_command(
+        &mut command,
+        "failed to open zellij session",
+        verbosity,
+        dry_run,
+    )?;
+
+    Ok(())
+}
+
+/// attaches to the entry's session if there is one (resurrecting it if it exited) or creates a
+/// new one
+fn push_zellij_args(
+    command: &mut Command,
+    entry: &Entry,
+    verbosity: Verbosity,
+) -> color_eyre::Result<()> {
+    let Some(name) = entry.session_name().as_deref().map(sanitize_session_name) else {
+        return Ok(());
+    };
+
+    match zellij_session_status(&name)? {
+        Some(_) => {
+            verbosity.info(format!("attaching to zellij session {name}"));
+            command.arg("attach")
+        }
+        None => {
+            verbosity.info(format!("creating zellij session {name}"));
+            command.arg("--session")
+        }
+    };
+    command.arg(&name);
+
+    Ok(())
+}
+
+/// opens the entry's directory in the platform's file manager without waiting for it to close
+pub fn open_in_file_manager(
+    entry: &Entry,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    let mut command = Command::new(opener);
+    command
+        .arg(&entry.path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    if dry_run {
+        println!("{command:?}");
+        return Ok(());
+    }
+
+    verbosity.info(format!("running {command:?}"));
+    command
+        .spawn()
+        .wrap_err_with(|| format!("unable to run {opener}"))?;
+
+    Ok(())
+}
+
+/// `$EDITOR`, the configured editor or `nvim`
+pub fn preferred_editor(config: &Config) -> String {
+    std::env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .or_else(|| config.editor.clone())
+        .unwrap_or_else(|| "nvim".to_string())
+}
+
+/// editors which open their own window and support `--new-window`
+const GUI_EDITORS: &[&str] = &["code", "code-insiders", "codium", "cursor", "zed", "subl"];
+
+/// whether the editor command starts one of the [gui editors](GUI_EDITORS)
+pub fn is_gui_editor(editor: &str) -> bool {
+    let program = editor.split_whitespace().next().unwrap_or_default();
+    Path::new(program)
+        .file_name()
+        .is_some_and(|name| GUI_EDITORS.contains(&name.to_string_lossy().as_ref()))
+}
+
+/// opens the entry, or `target` inside it, in the [preferred editor](preferred_editor), starting
+/// terminal editors inside a tmux session, in a new window if the session is already running
+pub fn open_in_editor(
+    entry: &Entry,
+    target: Option<PathBuf>,
+    new_window: bool,
+    config: &Config,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let editor = preferred_editor(config);
+    let program = editor.split_whitespace().next().unwrap_or_default();
+
+    let is_gui_editor = is_gui_editor(&editor);
+    if !is_gui_editor && cfg!(unix) {
+        let target = match &target {
+            Some(file) => shell_quote(&file.to_string_lossy()),
+            None => ".".to_string(),
+        };
+        let editor_command = format!("{editor} {target}");
+
+        // a running session is only attached to, so the editor gets a window of its own there
+        if let Some((name, true)) = tmux_target_session(entry, false, verbosity)? {
+            let mut command = Command::new("tmux");
+            command
+                .args(["new-window", "-t"])
+                .arg(format!("{name}:"))
+                .arg("-c")
+                .arg(entry.working_dir())
+                .arg(&editor_command);
+            if !run_command(&mut command, "failed to open editor", verbosity, dry_run)? {
+                return Ok(());
+            }
+        }
+
+        return open_tmux_session(entry, Some(&editor_command), false, verbosity, dry_run);
+    }
+
+    let mut command = Command::new(program);
+    command
+        .current_dir(entry.working_dir())
+        .args(editor.split_whitespace().skip(1));
+    if new_window && is_gui_editor {
+        command.arg("--new-window");
+    }
+    command.arg(target.as_deref().unwrap_or(&entry.path));
+
+    run_command(&mut command, "failed to open editor", verbosity, dry_run)?;
+
+    Ok(())
+}
+
+/// `arg` in single quotes, so the shell passes it on as is
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// attaches to the entry's session or creates it, running `initial_command` (or the entry's own
+/// command) in new sessions
+///
+/// when already running inside tmux the client is switched to the session instead, as attaching
+/// would nest sessions
+///
+/// for remote entries tmux is started on their host over ssh, which can't tell whether the
+/// session belongs to the entry, so `new_session` has no effect there
+pub fn open_tmux_session(
+    entry: &Entry,
+    initial_command: Option<&str>,
+    new_session: bool,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<()> {
+    let path = &entry.working_dir();
+    let initial_command = initial_command.or(entry.run.as_deref());
+
+    if let Some((host, remote_path)) = parse_remote(path) {
+        let mut command = remote_tmux_command(entry, &host, &remote_path, initial_command)?;
+        run_command(
+            &mut command,
+            "failed to open remote tmux session",
+            verbosity,
+            dry_run,
+        )?;
+
+        return Ok(());
+    }
+
+    let inside_tmux = std::env::var_os("TMUX").is_some();
+    let mut command = match tmux_target_session(entry, new_session, verbosity)? {
+        Some((name, exists)) => {
+            let (create_first, command) = tmux_client_command(
+                &name,
+                exists,
+                inside_tmux,
+                entry,
+                initial_command,
+                verbosity,
+            );
+            if create_first
+                && !new_detached_tmux_session(&name, entry, initial_command, verbosity, dry_run)?
+            {
+                return Ok(());
+            }
+            command
+        }
+        None => Command::new("tmux"),
+    };
+
+    run_command(
+        &mut command,
+        "failed to open tmux session",
+        verbosity,
+        dry_run,
+    )?;
+
+    Ok(())
+}
+
+/// the command getting the terminal into the session `name`, and whether the session has to be
+/// created in the background first
+///
+/// inside tmux the client is switched to the session, outside it's attached to or created right
+/// away, which blocks until detaching, so windows can only be added to sessions created before
+fn tmux_client_command(
+    name: &str,
+    exists: bool,
+    inside_tmux: bool,
+    entry: &Entry,
+    initial_command: Option<&str>,
+    verbosity: Verbosity,
+) -> (bool, Command) {
+    let mut command = Command::new("tmux");
+
+    if inside_tmux {
+        verbosity.info(format!("switching to tmux session {name}"));
+        command.args(["switch-client", "-t", name]);
+        (!exists, command)
+    } else if exists || !entry.windows.is_empty() {
+        verbosity.info(format!("attaching to tmux session {name}"));
+        command.args(["a", "-t", name]);
+        (!exists, command)
+    } else {
+        verbosity.info(format!("creating tmux session {name}"));
+        command
+            .args(["new", "-s", name, "-c"])
+            .arg(entry.working_dir())
+            .args(initial_command);
+        (false, command)
+    }
+}
+
+/// `ssh -t host` attaching to the entry's tmux session on the host, creating it in `path` if it
+/// doesn't exist yet
+fn remote_tmux_command(
+    entry: &Entry,
+    host: &str,
+    path: &Path,
+    initial_command: Option<&str>,
+) -> color_eyre::Result<Command> {
+    let name = entry
+        .session_name()
+        .as_deref()
+        .map(sanitize_session_name)
+        .ok_or_else(|| eyre!("unable to derive a session name for {entry}"))?;
+
+    // ssh joins its arguments into a single string for the remote shell, so they are quoted here
+    let mut remote = format!(
+        "tmux new -A -s {} -c {}",
+        shell_quote(&name),
+        shell_quote(&path.to_string_lossy())
+    );
+    if let Some(initial_command) = initial_command {
+        remote.push(' ');
+        remote.push_str(&shell_quote(initial_command));
+    }
+
+    let mut command = Command::new("ssh");
+    command.args(["-t", host, &remote]);
+
+    Ok(command)
+}
+
+/// creates the entry's session in the background if it doesn't exist yet and returns its name,
+/// so the caller can attach to or switch to it however it likes
+pub fn create_detached_tmux_session(
+    entry: &Entry,
+    new_session: bool,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<String> {
+    let Some((name, exists)) = tmux_target_session(entry, new_session, verbosity)? else {
+        return Err(eyre!("unable to derive a session name for {entry}"));
+    };
+
+    if exists {
+        verbosity.info(format!("tmux session {name} already exists"));
+    } else if !new_detached_tmux_session(&name, entry, entry.run.as_deref(), verbosity, dry_run)? {
+        return Err(eyre!("unable to create the tmux session {name}"));
+    }
+
+    Ok(name)
+}
+
+/// starts a tmux session for the entry without attaching to it, returning whether that worked
+///
+/// the first of the entry's windows is the one `initial_command` runs in, the others are added
+/// after it
+pub fn new_detached_tmux_session(
+    name: &str,
+    entry: &Entry,
+    initial_command: Option<&str>,
+    verbosity: Verbosity,
+    dry_run: bool,
+) -> color_eyre::Result<bool> {
+    let path = entry.working_dir();
+
+    verbosity.info(format!("creating tmux session {name}"));
+    let mut create = Command::new("tmux");
+    create
+        .args(["new-session", "-d", "-s", name, "-c"])
+        .arg(&path);
+    if let Some(window) = entry.windows.first() {
+        create.args(["-n", window]);
+    }
+    create.args(initial_command);
+
+    if !run_command(
+        &mut create,
+        "failed to create tmux session",
+        verbosity,
+        dry_run,
+    )? {
+        return Ok(false);
+    }
+
+    for window in entry.windows.iter().skip(1) {
+        let mut new_window = Command::new("tmux");
+        new_window
+            .args([
+                "new-window",
+                "-d",
+                "-t",
+                &format!("{name}:"),
+                "-n",
+                window,
+                "-c",
+            ])
+            .arg(&path);
+        if !run_command(
+            &mut new_window,
+            "failed to create tmux window",
+            verbosity,
+            dry_run,
+        )? {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// the session to open for the entry and whether it already exists
+///
+/// session names are derived from the last path component, so an existing session might belong
+/// to another project with the same name, [`tmux_session_conflict`] tells whether that's the case
+///
+/// with `new_session` an existing session is never reused, a suffixed one is created instead
+pub fn tmux_target_session(
+    entry: &Entry,
+    new_session: bool,
+    verbosity: Verbosity,
+) -> color_eyre::Result<Option<(String, bool)>> {
+    if entry.remote().is_some() {
+        return Err(eyre!(
+            "{entry} is on another host, only `open` can attach to its tmux session there"
+        ));
+    }
+
+    let Some(name) = entry.session_name().as_deref().map(sanitize_session_name) else {
+        return Ok(None);
+    };
+
+    let sessions = list_tmux_sessions()?;
+    if !tmux_session_exists(&sessions, &name) {
+        return Ok(Some((name, false)));
+    }
+
+    if new_session {
+        let new_name = unique_tmux_session_name(&sessions, &name);
+        verbosity.info(format!(
+            "tmux session {name} already exists, using {new_name} instead"
+        ));
+        return Ok(Some((new_name, false)));
+    }
+
+    Ok(Some((name, true)))
+}
+
+/// a running tmux session with the entry's name that was started in another directory
+pub struct SessionConflict {
+    pub name: String,
+
+    /// the directory the session was started in
+    pub session_path: PathBuf,
+
+    /// the name the session gets when opening the entry with `new_session`
+    pub new_name: String,
+}
+
+/// the session the entry would attach to if it was started in another directory, which likely
+/// means it belongs to another project with the same name
+///
+/// remote sessions can't be checked, so they never conflict
+pub fn tmux_session_conflict(entry: &Entry) -> color_eyre::Result<Option<SessionConflict>> {
+    if entry.remote().is_some() {
+        return Ok(None);
+    }
+    let Some(name) = entry.session_name().as_deref().map(sanitize_session_name) else {
+        return Ok(None);
+    };
+
+    let sessions = list_tmux_sessions()?;
+    if !tmux_session_exists(&sessions, &name) {
+        return Ok(None);
+    }
+
+    let Some(session_path) = tmux_session_path(&name)? else {
+        return Ok(None);
+    };
+    if normalized_path(&session_path) == normalized_path(&entry.working_dir()) {
+        return Ok(None);
+    }
+
+    Ok(Some(SessionConflict {
+        new_name: unique_tmux_session_name(&sessions, &name),
+        name,
+        session_path,
+    }))
+}
+
+/// the directory the tmux session was started in
+fn tmux_session_path(name: &str) -> color_eyre::Result<Option<PathBuf>> {
+    let output = Command::new("tmux")
+        .args(["display-message", "-p", "-t"])
+        .arg(name)
+        .arg("#{session_path}")
+        .output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let path = String::from_utf8(output.stdout)?;
+    let path = path.trim();
+    Ok((!path.is_empty()).then(|| PathBuf::from(path)))
+}
+
+/// the first of `name-2`, `name-3`, ... that isn't used by a tmux session yet
+fn unique_tmux_session_name(sessions: &[String], name: &str) -> String {
+    (2..)
+        .map(|n| format!("{name}-{n}"))
+        .find(|candidate| !tmux_session_exists(sessions, candidate))
+        .expect("there should be an unused suffix")
+}
+
+fn tmux_session_exists(sessions: &[String], session_name: &str) -> bool {
+    sessions.iter().any(|existing| existing == session_name)
+}
+
+/// names of all running tmux sessions, which are none if there is no tmux server yet
+///
+/// this runs `tmux ls`, so callers needing multiple lookups should only fetch them once
+pub fn list_tmux_sessions() -> color_eyre::Result<Vec<String>> {
+    let output = Command::new("tmux")
+        .args(["ls", "-F", "#{session_name}"])
+        .output()
+        .wrap_err("unable to run tmux, is it installed?")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_no_server_error(&stderr) {
+            return Ok(Vec::new());
+        }
+
+        return Err(eyre!(
+            "tmux ls failed ({}): {}",
+            output.status,
+            stderr.trim()
+        ));
+    }
+
+    let stdout =
+        String::from_utf8(output.stdout).wrap_err("expected tmux ls to output valid utf-8")?;
+    Ok(parse_session_names(&stdout))
+}
+
+/// names of the sessions of the multiplexer which are running, none if it isn't installed
+pub fn running_sessions(multiplexer: Multiplexer) -> Vec<String> {
+    match multiplexer {
+        Multiplexer::Tmux => list_tmux_sessions().unwrap_or_default(),
+        Multiplexer::Zellij => zellij_sessions()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|session| session.status != Status::Exited)
+            .map(|session| session.name)
+            .collect(),
+        Multiplexer::None => Vec::new(),
+    }
+}
+
+/// whether `tmux ls` failed only because no server is running, i.e. there are no sessions
+fn is_no_server_error(stderr: &str) -> bool {
+    stderr.contains("no server running") || stderr.contains("error connecting to")
+}
+
+/// parses the output of `tmux ls -F '#{session_name}'`
+fn parse_session_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// tmux uses `.` and `:` to address windows and panes within a target, so they can't be part of a
+/// session name that we later want to attach to
+pub fn sanitize_session_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '.' | ':' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<&str> {
+        command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn switches_client_inside_tmux() {
+        let entry = Entry::new("/code/api".into());
+
+        let (create_first, command) =
+            tmux_client_command("api", true, true, &entry, None, Verbosity::Quiet);
+        assert!(!create_first);
+        assert_eq!(args(&command), ["switch-client", "-t", "api"]);
+
+        let (create_first, command) =
+            tmux_client_command("api", false, true, &entry, None, Verbosity::Quiet);
+        assert!(create_first);
+        assert_eq!(args(&command), ["switch-client", "-t", "api"]);
+    }
+
+    #[test]
+    fn attaches_or_creates_outside_tmux() {
+        let entry = Entry::new("/code/api".into());
+
+        let (create_first, command) =
+            tmux_client_command("api", true, false, &entry, None, Verbosity::Quiet);
+        assert!(!create_first);
+        assert_eq!(args(&command), ["a", "-t", "api"]);
+
+        let (create_first, command) =
+            tmux_client_command("api", false, false, &entry, Some("make"), Verbosity::Quiet);
+        assert!(!create_first);
+        assert_eq!(
+            args(&command),
+            ["new", "-s", "api", "-c", "/code/api", "make"]
+        );
+    }
+
+    #[test]
+    fn creates_sessions_with_windows_before_attaching() {
+        let entry = Entry {
+            windows: vec!["editor".to_string()],
+            ..Entry::new("/code/api".into())
+        };
+
+        let (create_first, command) =
+            tmux_client_command("api", false, false, &entry, None, Verbosity::Quiet);
+        assert!(create_first);
+        assert_eq!(args(&command), ["a", "-t", "api"]);
+    }
+
+    #[test]
+    fn parses_session_names() {
+        assert_eq!(
+            parse_session_names("api\nv1_2\n  padded  \n\nwork/web\n"),
+            ["api", "v1_2", "padded", "work/web"]
+        );
+        assert!(parse_session_names("").is_empty());
+    }
+
+    #[test]
+    fn session_names_match_exactly() {
+        let sessions = parse_session_names("api-server\nweb\n");
+
+        assert!(tmux_session_exists(&sessions, "web"));
+        assert!(!tmux_session_exists(&sessions, "api"));
+        assert!(!tmux_session_exists(&sessions, "we"));
+    }
+
+    #[test]
+    fn tells_missing_server_from_other_errors() {
+        assert!(is_no_server_error(
+            "no server running on /tmp/tmux-1000/default\n"
+        ));
+        assert!(is_no_server_error(
+            "error connecting to /tmp/tmux-1000/default (No such file or directory)\n"
+        ));
+        assert!(!is_no_server_error("unknown option -- F\n"));
+    }
+}
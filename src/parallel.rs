@@ -0,0 +1,39 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+/// upper bound of threads working at the same time
+const MAX_WORKERS: usize = 8;
+
+/// maps `items` with `f` on a small pool of threads, keeping the order of the input
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .clamp(1, MAX_WORKERS)
+        .min(items.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(idx) else {
+                    break;
+                };
+                let result = f(item);
+                results
+                    .lock()
+                    .expect("no worker should panic while holding the lock")
+                    .push((idx, result));
+            });
+        }
+    });
+
+    let mut results = results
+        .into_inner()
+        .expect("no worker should panic while holding the lock");
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
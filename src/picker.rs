@@ -0,0 +1,285 @@
+use std::cmp::Reverse;
+
+use color_eyre::eyre::eyre;
+use console::{strip_ansi_codes, style, truncate_str, Key, Term};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+/// how much more a match in the name or a keyword counts than one in the rest of the label
+const NAME_WEIGHT: i64 = 2;
+
+/// an entry as it's shown in the picker
+pub struct Item {
+    /// may contain ansi escapes, which are ignored when matching
+    pub label: String,
+
+    /// the last path component, matches in it rank above matches in the rest of the label, so
+    /// `api` finds `~/code/api` before `~/api-notes/src`
+    pub name: String,
+
+    /// matched after the label without being shown, like the tags of the entry, a match in one of
+    /// them ranks like one in the name so all entries with a tag come first when typing it
+    pub keywords: Vec<String>,
+}
+
+/// a label that matches the query, with the indices of the matched characters
+struct Match {
+    idx: usize,
+    indices: Vec<usize>,
+}
+
+/// called with the index of the highlighted item when ctrl-x is pressed, returns the indices of
+/// all items that are gone afterwards
+pub type Remove<'a> = dyn FnMut(usize) -> color_eyre::Result<Vec<usize>> + 'a;
+
+/// lets the user pick one of the items by typing a fuzzy query, the matching labels are listed
+/// best match first with the matched characters highlighted
+///
+/// with `remove`, ctrl-x removes the highlighted item and keeps the picker open
+///
+/// returns the index of the picked item, `None` if the user cancelled
+pub fn fuzzy_select(
+    items: &[Item],
+    default: usize,
+    remove: Option<&mut Remove>,
+) -> color_eyre::Result<Option<usize>> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(eyre!("the builtin picker needs a terminal"));
+    }
+
+    term.hide_cursor()?;
+    let selection = run(&term, items, default, remove);
+    term.show_cursor()?;
+
+    selection
+}
+
+fn run(
+    term: &Term,
+    items: &[Item],
+    default: usize,
+    mut remove: Option<&mut Remove>,
+) -> color_eyre::Result<Option<usize>> {
+    // matching ignores the decorations, they are dropped once there is a query
+    let plain: Vec<String> = items
+        .iter()
+        .map(|item| strip_ansi_codes(&item.label).into_owned())
+        .collect();
+    // the keywords come last, so the indices of matches in the label stay the same
+    let searchable: Vec<String> = plain
+        .iter()
+        .zip(items)
+        .map(|(plain, item)| {
+            std::iter::once(plain.as_str())
+                .chain(item.keywords.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    let matcher = SkimMatcherV2::default().smart_case();
+
+    let mut query = String::new();
+    let mut removed = vec![false; items.len()];
+    let mut matches = find_matches(&matcher, &searchable, items, &removed, &query);
+    let mut cursor = default.min(items.len().saturating_sub(1));
+    let mut rendered = 0;
+
+    let selection = loop {
+        term.clear_last_lines(rendered)?;
+        let remaining = removed.iter().filter(|removed| !**removed).count();
+        rendered = render(term, items, &plain, &query, &matches, cursor, remaining)?;
+
+        match term.read_key()? {
+            Key::Enter if !matches.is_empty() => break Some(matches[cursor].idx),
+            Key::Escape | Key::CtrlC => break None,
+            Key::ArrowUp if !matches.is_empty() => {
+                cursor = cursor.checked_sub(1).unwrap_or(matches.len() - 1);
+            }
+            Key::ArrowDown if !matches.is_empty() => cursor = (cursor + 1) % matches.len(),
+            Key::Backspace if query.pop().is_some() => {
+                matches = find_matches(&matcher, &searchable, items, &removed, &query);
+                cursor = 0;
+            }
+            Key::Char('\x18') if !matches.is_empty() => {
+                let Some(remove) = remove.as_mut() else {
+                    continue;
+                };
+                for idx in remove(matches[cursor].idx)? {
+                    removed[idx] = true;
+                }
+                matches = find_matches(&matcher, &searchable, items, &removed, &query);
+                cursor = cursor.min(matches.len().saturating_sub(1));
+            }
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                matches = find_matches(&matcher, &searchable, items, &removed, &query);
+                cursor = 0;
+            }
+            _ => {}
+        }
+    };
+    term.clear_last_lines(rendered)?;
+
+    Ok(selection)
+}
+
+/// the items whose searchable string matches `query` and which weren't removed, sorted by score,
+/// items with the same score keep their order
+///
+/// the best match in the name or a keyword is added to the score of the whole label with
+/// [`NAME_WEIGHT`]
+fn find_matches(
+    matcher: &SkimMatcherV2,
+    searchable: &[String],
+    items: &[Item],
+    removed: &[bool],
+    query: &str,
+) -> Vec<Match> {
+    if query.is_empty() {
+        return (0..searchable.len())
+            .filter(|idx| !removed[*idx])
+            .map(|idx| Match {
+                idx,
+                indices: Vec::new(),
+            })
+            .collect();
+    }
+
+    let mut scored: Vec<(i64, Match)> = searchable
+        .iter()
+        .zip(items)
+        .enumerate()
+        .filter(|(idx, _)| !removed[*idx])
+        .filter_map(|(idx, (label, item))| {
+            let (score, indices) = matcher.fuzzy_indices(label, query)?;
+            let name_score = std::iter::once(&item.name)
+                .chain(&item.keywords)
+                .filter_map(|word| matcher.fuzzy_match(word, query))
+                .max()
+                .unwrap_or_default();
+            Some((score + name_score * NAME_WEIGHT, Match { idx, indices }))
+        })
+        .collect();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+
+    scored.into_iter().map(|(_, m)| m).collect()
+}
+
+/// draws the prompt and the visible part of the list, returning the number of lines written
+fn render(
+    term: &Term,
+    items: &[Item],
+    plain: &[String],
+    query: &str,
+    matches: &[Match],
+    cursor: usize,
+    total: usize,
+) -> std::io::Result<usize> {
+    let (height, width) = term.size();
+    let visible = (height as usize).saturating_sub(2).max(1);
+    let start = cursor.saturating_sub(visible - 1);
+
+    term.write_line(&format!(
+        "{} {query}  {}",
+        style(">").for_stderr().green().bold(),
+        style(format!("{}/{total}", matches.len()))
+            .for_stderr()
+            .dim()
+    ))?;
+
+    let mut written = 1;
+    for (pos, m) in matches.iter().enumerate().skip(start).take(visible) {
+        let label = if query.is_empty() {
+            items[m.idx].label.clone()
+        } else {
+            highlight(&plain[m.idx], &m.indices)
+        };
+        let marker = if pos == cursor {
+            style("❯").for_stderr().green().bold().to_string()
+        } else {
+            " ".to_string()
+        };
+
+        let line = format!("{marker} {label}");
+        term.write_line(&truncate_str(&line, width as usize, "…"))?;
+        written += 1;
+    }
+
+    Ok(written)
+}
+
+/// `label` with the characters at `indices` in bold, plain if colors are disabled, indices past
+/// the end of the label are matches in the keywords
+fn highlight(label: &str, indices: &[usize]) -> String {
+    label
+        .chars()
+        .enumerate()
+        .map(|(idx, c)| {
+            if indices.contains(&idx) {
+                style(c).for_stderr().cyan().bold().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the indices of the `(label, keywords)` items matching `query`, best match first, with the
+    /// last path component of the label as the name like the entries have
+    fn matched(items: &[(&str, &[&str])], query: &str) -> Vec<usize> {
+        let items: Vec<Item> = items
+            .iter()
+            .map(|(label, keywords)| Item {
+                label: label.to_string(),
+                name: label.rsplit('/').next().unwrap_or(label).to_string(),
+                keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            })
+            .collect();
+        let searchable: Vec<String> = items
+            .iter()
+            .map(|item| [item.label.clone(), item.keywords.join(" ")].join(" "))
+            .collect();
+        let removed = vec![false; items.len()];
+        let matcher = SkimMatcherV2::default().smart_case();
+
+        find_matches(&matcher, &searchable, &items, &removed, query)
+            .into_iter()
+            .map(|m| m.idx)
+            .collect()
+    }
+
+    #[test]
+    fn matches_tags_only_of_tagged_entries() {
+        let items: &[(&str, &[&str])] = &[
+            ("~/code/web", &["backend"]),
+            ("~/code/api", &[]),
+            ("~/notes", &[]),
+            ("~/code/db", &["backend", "work"]),
+        ];
+
+        assert_eq!(matched(items, "backend"), [0, 3]);
+        assert_eq!(matched(items, "work"), [3]);
+    }
+
+    #[test]
+    fn ranks_tagged_entries_above_partial_matches() {
+        let items: &[(&str, &[&str])] = &[
+            ("~/w/o/r/k", &[]),
+            ("~/code/web", &["work"]),
+            ("~/code/api", &[]),
+        ];
+
+        assert_eq!(matched(items, "work"), [1, 0]);
+    }
+
+    #[test]
+    fn ranks_matching_names_first() {
+        let items: &[(&str, &[&str])] = &[("~/api-tools/x", &[]), ("~/code/api", &[])];
+
+        assert_eq!(matched(items, "api"), [1, 0]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::process::Command;
+
+use color_eyre::eyre::Context;
+use serde::Serialize;
+use winnow::{
+    ascii::{space0, space1},
+    combinator::{delimited, opt},
+    token::take_till,
+    ModalResult, Parser,
+};
+
+/// serialized as `{ "name": ..., "info": ... | null, "status": "running" | "current" | "exited" }`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ZellijSession {
+    pub name: String,
+
+    /// whatever zellij printed in brackets after the name, usually when it was created
+    pub info: Option<String>,
+
+    pub status: Status,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// running, but we aren't attached to it
+    Running,
+
+    /// the session we're currently attached to
+    Current,
+
+    /// no longer running, attaching to it resurrects it
+    Exited,
+}
+
+/// the status of the zellij session called `name`, if there is one
+pub fn zellij_session_status(name: &str) -> color_eyre::Result<Option<Status>> {
+    Ok(zellij_sessions()?
+        .into_iter()
+        .find(|session| session.name == name)
+        .map(|session| session.status))
+}
+
+pub fn zellij_sessions() -> color_eyre::Result<Vec<ZellijSession>> {
+    let output = Command::new("zellij")
+        .args(["list-sessions", "--no-formatting"])
+        .output()
+        .wrap_err("unable to run zellij, is it installed?")?;
+
+    // zellij exits with an error if there are no sessions at all
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let stdout =
+        String::from_utf8(output.stdout).wrap_err("expected zellij ls to output valid utf-8")?;
+    Ok(parse_zellij_ls(&stdout))
+}
+
+/// parses the output of `zellij ls --no-formatting`, skipping lines that can't be parsed
+pub fn parse_zellij_ls(output: &str) -> Vec<ZellijSession> {
+    output
+        .lines()
+        .filter_map(|mut line| session_line.parse_next(&mut line).ok())
+        .collect()
+}
+
+/// `name [info] (status)` where both info and status are optional
+fn session_line(input: &mut &str) -> ModalResult<ZellijSession> {
+    let name = session_name.parse_next(input)?;
+    let info = opt((space1, bracketed).map(|(_, info)| info)).parse_next(input)?;
+    let _ = space0.parse_next(input)?;
+    let status = opt(status).parse_next(input)?;
+
+    Ok(ZellijSession {
+        name: name.to_string(),
+        info: info.map(|info| info.trim().to_string()),
+        status: status.unwrap_or(Status::Running),
+    })
+}
+
+/// zellij's generated names are `adjective-noun`, but user given ones may contain nearly anything
+/// (like `v1.2` or `work/api`) except whitespace
+fn session_name<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
+    take_till(1.., char::is_whitespace).parse_next(input)
+}
+
+/// newer versions of zellij print things like `[Created 1h 2m 3s ago]`, so anything up to the
+/// closing bracket is accepted
+fn bracketed<'a>(input: &mut &'a str) -> ModalResult<&'a str> {
+    delimited('[', take_till(0.., ']'), ']').parse_next(input)
+}
+
+fn status(input: &mut &str) -> ModalResult<Status> {
+    delimited('(', take_till(0.., ')'), ')')
+        .map(|status: &str| {
+            if status.starts_with("EXITED") {
+                Status::Exited
+            } else if status == "current" {
+                Status::Current
+            } else {
+                Status::Running
+            }
+        })
+        .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, info: Option<&str>, status: Status) -> ZellijSession {
+        ZellijSession {
+            name: name.to_string(),
+            info: info.map(str::to_string),
+            status,
+        }
+    }
+
+    #[test]
+    fn parses_created_durations() {
+        let output = "happy-cat [Created 1h 2m 3s ago] \n\
+                      sad-dog [Created 5s ago] (current)\n\
+                      old-fox [Created 2days ago] (EXITED - attach to resurrect)\n";
+
+        assert_eq!(
+            parse_zellij_ls(output),
+            [
+                session("happy-cat", Some("Created 1h 2m 3s ago"), Status::Running),
+                session("sad-dog", Some("Created 5s ago"), Status::Current),
+                session("old-fox", Some("Created 2days ago"), Status::Exited),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_punctuation_in_brackets() {
+        assert_eq!(
+            parse_zellij_ls("work [Created: 1h, 2m (ish)!]"),
+            [session(
+                "work",
+                Some("Created: 1h, 2m (ish)!"),
+                Status::Running
+            )]
+        );
+    }
+
+    #[test]
+    fn parses_lines_without_suffix() {
+        assert_eq!(
+            parse_zellij_ls("happy-cat\n\nsad-dog (current)\n"),
+            [
+                session("happy-cat", None, Status::Running),
+                session("sad-dog", None, Status::Current),
+            ]
+        );
+    }
+
+    #[test]
+    fn session_names_keep_dots() {
+        let mut input = "v1.2 [Created 3s ago]";
+
+        assert_eq!(session_name(&mut input).unwrap(), "v1.2");
+        assert_eq!(input, " [Created 3s ago]");
+    }
+
+    #[test]
+    fn session_names_keep_slashes() {
+        assert_eq!(session_name(&mut "work/api").unwrap(), "work/api");
+        assert_eq!(
+            parse_zellij_ls("work/api (current)"),
+            [session("work/api", None, Status::Current)]
+        );
+    }
+
+    #[test]
+    fn parses_generated_session_names() {
+        assert_eq!(
+            session_name(&mut "quiet-lemur (current)").unwrap(),
+            "quiet-lemur"
+        );
+    }
+}